@@ -0,0 +1,395 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Input actions, decoupling game logic from the physical controls.
+//!
+//! Instead of matching on `KeyCode`s in `App::key_down`, a game declares named actions
+//! such as "jump" or "move_x" in an `InputMap`, binds them to keys, mouse buttons, touch
+//! regions or gamepad inputs, and queries the actions every frame.
+//! Bindings can be saved to and loaded from a simple text format, which makes it
+//! straightforward to build a controls settings menu.
+//!
+//! ```
+//! use nuuro::actions::{Binding, InputMap};
+//! use nuuro::{GamepadAxis, GamepadButton, KeyCode};
+//!
+//! let mut input = InputMap::new();
+//! input.bind("jump", Binding::Key(KeyCode::Space));
+//! input.bind("jump", Binding::GamepadButton(GamepadButton::South));
+//! input.bind_axis("move_x", Binding::Key(KeyCode::Left), Binding::Key(KeyCode::Right));
+//! input.bind("move_x", Binding::GamepadAxis(GamepadAxis::LeftStickX));
+//!
+//! let saved = input.save_bindings();
+//! assert!(saved.contains("jump key:Space"));
+//!
+//! let mut restored = InputMap::new();
+//! restored.load_bindings(&saved).unwrap();
+//! assert_eq!(restored.save_bindings(), saved);
+//! ```
+//!
+//! In `App::advance`, call `InputMap::update` once and then query the actions with
+//! `pressed`, `just_pressed`, `just_released` or `value`.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::asset_id::AppAssetId;
+use crate::gamepad::{Gamepad, GamepadAxis, GamepadButton, GamepadId};
+use crate::input::KeyCode;
+use crate::AppContext;
+
+const PRESS_THRESHOLD: f32 = 0.5;
+
+/// A physical input that can be bound to an action.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Binding {
+    /// A keyboard key or mouse button.
+    Key(KeyCode),
+    /// A gamepad button, analog triggers give values between `0` and `1`.
+    GamepadButton(GamepadButton),
+    /// A gamepad stick axis, giving values between `-1` and `1`.
+    GamepadAxis(GamepadAxis),
+    /// A rectangle in app coordinates that is active while touched, with `(x, y)`
+    /// being its bottom-left corner.
+    TouchRegion { x: f64, y: f64, w: f64, h: f64 },
+}
+
+impl Binding {
+    fn value<A: AppAssetId>(&self, ctx: &AppContext<A>, gamepad: Option<GamepadId>) -> f32 {
+        let pads = ctx.gamepads().iter().filter(move |pad| match gamepad {
+            Some(id) => pad.id() == id,
+            None => true,
+        });
+        match *self {
+            Binding::Key(key) => {
+                if ctx.is_key_down(key) {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Binding::GamepadButton(button) => {
+                pads.map(|pad| pad.button_value(button)).fold(0., f32::max)
+            }
+            Binding::GamepadAxis(axis) => pads
+                .map(|pad: &Gamepad| pad.axis(axis))
+                .fold(0., |a: f32, b: f32| if b.abs() > a.abs() { b } else { a }),
+            Binding::TouchRegion { x, y, w, h } => {
+                let touched = ctx.touches().iter().any(|touch| {
+                    let (tx, ty) = touch.pos();
+                    tx >= x && tx <= x + w && ty >= y && ty <= y + h
+                });
+                if touched {
+                    1.
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+
+    fn parse(s: &str) -> Option<Binding> {
+        let (kind, name) = s.split_once(':')?;
+        match kind {
+            "key" => (0..)
+                .map_while(KeyCode::from_u8)
+                .find(|key| format!("{:?}", key) == name)
+                .map(Binding::Key),
+            "button" => (0..)
+                .map_while(GamepadButton::from_u8)
+                .find(|button| format!("{:?}", button) == name)
+                .map(Binding::GamepadButton),
+            "axis" => (0..)
+                .map_while(GamepadAxis::from_u8)
+                .find(|axis| format!("{:?}", axis) == name)
+                .map(Binding::GamepadAxis),
+            "touch" => {
+                let rect: Vec<f64> = name
+                    .split(',')
+                    .map(|v| v.trim().parse().ok())
+                    .collect::<Option<_>>()?;
+                match rect[..] {
+                    [x, y, w, h] => Some(Binding::TouchRegion { x, y, w, h }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Formats the binding as it appears in `InputMap::save_bindings`,
+/// e.g. `key:Space` or `button:South`.
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{:?}", key),
+            Binding::GamepadButton(button) => write!(f, "button:{:?}", button),
+            Binding::GamepadAxis(axis) => write!(f, "axis:{:?}", axis),
+            Binding::TouchRegion { x, y, w, h } => write!(f, "touch:{},{},{},{}", x, y, w, h),
+        }
+    }
+}
+
+/// A binding attached to an action.
+///
+/// Negative bindings subtract from the action value, which is how both
+/// directions of an axis action are bound to buttons.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundInput {
+    pub binding: Binding,
+    pub negative: bool,
+}
+
+struct Action {
+    name: String,
+    bindings: Vec<BoundInput>,
+    value: f32,
+    pressed: bool,
+    was_pressed: bool,
+}
+
+/// Error returned by `InputMap::load_bindings`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseBindingsError {
+    line: usize,
+    text: String,
+}
+
+impl fmt::Display for ParseBindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid binding on line {}: {:?}", self.line, self.text)
+    }
+}
+
+impl Error for ParseBindingsError {}
+
+/// Set of named actions and the inputs bound to them.
+///
+/// Queries for actions that were never bound return `false` or `0`.
+#[derive(Default)]
+pub struct InputMap {
+    actions: Vec<Action>,
+    gamepad: Option<GamepadId>,
+}
+
+impl InputMap {
+    /// Creates an empty input map.
+    pub fn new() -> InputMap {
+        InputMap::default()
+    }
+
+    fn action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    fn existing_action_mut(&mut self, name: &str) -> Option<&mut Action> {
+        self.actions.iter_mut().find(|action| action.name == name)
+    }
+
+    fn action_mut(&mut self, name: &str) -> &mut Action {
+        if let Some(index) = self.actions.iter().position(|action| action.name == name) {
+            &mut self.actions[index]
+        } else {
+            self.actions.push(Action {
+                name: name.to_string(),
+                bindings: Vec::new(),
+                value: 0.,
+                pressed: false,
+                was_pressed: false,
+            });
+            self.actions.last_mut().unwrap()
+        }
+    }
+
+    /// Binds an input to the given action, creating the action if needed.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        self.bind_input(
+            action,
+            BoundInput {
+                binding,
+                negative: false,
+            },
+        );
+    }
+
+    /// Binds an input that pushes the given axis action towards `-1`.
+    pub fn bind_negative(&mut self, action: &str, binding: Binding) {
+        self.bind_input(
+            action,
+            BoundInput {
+                binding,
+                negative: true,
+            },
+        );
+    }
+
+    /// Binds a pair of inputs pushing the given axis action towards `-1` and `1`,
+    /// such as the left and right arrow keys.
+    pub fn bind_axis(&mut self, action: &str, negative: Binding, positive: Binding) {
+        self.bind_negative(action, negative);
+        self.bind(action, positive);
+    }
+
+    fn bind_input(&mut self, action: &str, input: BoundInput) {
+        assert!(
+            !action.is_empty() && !action.contains(char::is_whitespace),
+            "invalid action name: {:?}",
+            action
+        );
+        let action = self.action_mut(action);
+        if !action.bindings.contains(&input) {
+            action.bindings.push(input);
+        }
+    }
+
+    /// Removes a binding from the given action, in either direction.
+    ///
+    /// Does nothing if the action was never bound.
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(action) = self.existing_action_mut(action) {
+            action.bindings.retain(|input| input.binding != binding);
+        }
+    }
+
+    /// Removes all bindings from the given action.
+    ///
+    /// Does nothing if the action was never bound.
+    pub fn clear_bindings(&mut self, action: &str) {
+        if let Some(action) = self.existing_action_mut(action) {
+            action.bindings.clear();
+        }
+    }
+
+    /// Returns the inputs bound to the given action.
+    pub fn bindings(&self, action: &str) -> &[BoundInput] {
+        self.action(action)
+            .map(|action| &action.bindings[..])
+            .unwrap_or(&[])
+    }
+
+    /// Returns the names of all actions, in the order they were first bound.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|action| action.name.as_str())
+    }
+
+    /// Restricts gamepad bindings to a single gamepad, e.g. for local multiplayer.
+    ///
+    /// By default (`None`), all connected gamepads are considered.
+    pub fn set_gamepad(&mut self, gamepad: Option<GamepadId>) {
+        self.gamepad = gamepad;
+    }
+
+    /// Reads the current input state from `ctx`.
+    ///
+    /// This should be called once at the start of every `App::advance`, as
+    /// `just_pressed` and `just_released` compare against the previous call.
+    pub fn update<A: AppAssetId>(&mut self, ctx: &AppContext<A>) {
+        let gamepad = self.gamepad;
+        for action in &mut self.actions {
+            let value: f32 = action
+                .bindings
+                .iter()
+                .map(|input| {
+                    let value = input.binding.value(ctx, gamepad);
+                    if input.negative {
+                        -value
+                    } else {
+                        value
+                    }
+                })
+                .sum();
+            action.value = value.clamp(-1., 1.);
+            action.was_pressed = action.pressed;
+            action.pressed = action.value.abs() >= PRESS_THRESHOLD;
+        }
+    }
+
+    /// Returns the action value, between `-1` and `1`.
+    ///
+    /// Keys and buttons contribute `1` (or `-1` if bound as negative) while held,
+    /// analog inputs contribute their current value.
+    pub fn value(&self, action: &str) -> f32 {
+        self.action(action).map_or(0., |action| action.value)
+    }
+
+    /// Checks whether the action is held, i.e. its value is at least `0.5` away from zero.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.action(action).is_some_and(|action| action.pressed)
+    }
+
+    /// Checks whether the action became pressed since the previous `update`.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.action(action)
+            .is_some_and(|action| action.pressed && !action.was_pressed)
+    }
+
+    /// Checks whether the action stopped being pressed since the previous `update`.
+    pub fn just_released(&self, action: &str) -> bool {
+        self.action(action)
+            .is_some_and(|action| !action.pressed && action.was_pressed)
+    }
+
+    /// Serializes all bindings to text, one `action binding` pair per line.
+    ///
+    /// Negative bindings are prefixed with `-`, e.g. `move_x -key:Left`.
+    pub fn save_bindings(&self) -> String {
+        let mut text = String::new();
+        for action in &self.actions {
+            for input in &action.bindings {
+                let sign = if input.negative { "-" } else { "" };
+                text.push_str(&format!("{} {}{}\n", action.name, sign, input.binding));
+            }
+        }
+        text
+    }
+
+    /// Loads bindings produced by `save_bindings`.
+    ///
+    /// Actions that appear in `text` have their bindings replaced, other actions keep
+    /// their current bindings, so defaults for newly added actions survive loading an
+    /// older settings file. Empty lines and lines starting with `#` are ignored.
+    /// Nothing is changed if any line fails to parse.
+    pub fn load_bindings(&mut self, text: &str) -> Result<(), ParseBindingsError> {
+        let mut loaded: Vec<(&str, BoundInput)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || ParseBindingsError {
+                line: index + 1,
+                text: line.to_string(),
+            };
+            let mut parts = line.split_whitespace();
+            let (action, binding) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(action), Some(binding), None) => (action, binding),
+                _ => return Err(error()),
+            };
+            let (negative, binding) = match binding.strip_prefix('-') {
+                Some(binding) => (true, binding),
+                None => (false, binding),
+            };
+            let binding = Binding::parse(binding).ok_or_else(error)?;
+            loaded.push((action, BoundInput { binding, negative }));
+        }
+        for &(action, _) in &loaded {
+            self.clear_bindings(action);
+        }
+        for (action, input) in loaded {
+            self.bind_input(action, input);
+        }
+        Ok(())
+    }
+}
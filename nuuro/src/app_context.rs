@@ -12,52 +12,136 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::marker::PhantomData;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 use crate::asset_id::{AppAssetId, IdU16};
-use crate::core::CoreAudio;
-use crate::input::TouchPoint;
+use crate::config::Config;
+use crate::core::{CoreAudio, CoreStorage};
+use crate::gamepad::{Gamepad, GamepadId, Gamepads};
+use crate::input::{KeyCode, Modifiers, TouchPoint};
+use crate::storage::{Storage, StorageError};
+use crate::synth::{SynthParams, SynthSoundId, SAMPLE_RATE};
+use crate::timer::TimerSet;
 
 /// Context passed to methods in `App`.
 pub struct AppContext<A: AppAssetId> {
     /// Audio playback.
     pub audio: Audio<A>,
+    /// Persistent save data.
+    pub storage: Storage,
+    /// Timers and tweens advancing every frame, right before `App::advance`, following the
+    /// time scale.
+    pub timers: TimerSet,
+    /// Timers and tweens advancing every frame at real speed, even while paused, e.g. to
+    /// animate menus.
+    pub ui_timers: TimerSet,
     dims: (f64, f64),
     cursor: (f64, f64),
     touches: Vec<TouchPoint>,
+    held_keys: HashSet<KeyCode>,
+    pressed_keys: HashSet<KeyCode>,
+    released_keys: HashSet<KeyCode>,
+    cursor_delta: (f64, f64),
+    mouse_motion: (f64, f64),
+    cursor_visible: bool,
+    cursor_sprite: Option<u16>,
+    is_pointer_locked: bool,
+    desires_pointer_lock: bool,
+    is_focused: bool,
+    is_suspended: bool,
+    pause_when_inactive: bool,
+    time_scale: f64,
+    is_paused: bool,
+    unscaled_seconds: f64,
+    modifiers: Modifiers,
+    pub(crate) gamepads: Gamepads,
+    text_input: bool,
     close_requested: bool,
     native_px: f64,
     is_fullscreen: bool,
     desires_fullscreen: bool,
+    config: Config,
     cookie: Vec<u8>,
     cookie_updated: bool,
 }
 
 impl<A: AppAssetId> AppContext<A> {
-    pub(crate) fn new(audio: CoreAudio, dims: (f64, f64), native_px: f64) -> AppContext<A> {
+    pub(crate) fn new(
+        audio: CoreAudio,
+        storage: CoreStorage,
+        save_schema_version: u32,
+        dims: (f64, f64),
+        native_px: f64,
+    ) -> AppContext<A> {
         AppContext {
             audio: Audio {
                 core: audio,
+                master_volume: 1.,
+                music_volume: 1.,
+                sound_volume: 1.,
+                music_base_volume: 1.,
                 phantom: PhantomData,
             },
+            storage: Storage::new(storage, save_schema_version),
+            timers: TimerSet::new(),
+            ui_timers: TimerSet::new(),
             dims,
             cursor: (0., 0.),
             touches: Vec::new(),
+            held_keys: HashSet::new(),
+            pressed_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+            cursor_delta: (0., 0.),
+            mouse_motion: (0., 0.),
+            cursor_visible: true,
+            cursor_sprite: None,
+            is_pointer_locked: false,
+            desires_pointer_lock: false,
+            is_focused: true,
+            is_suspended: false,
+            pause_when_inactive: false,
+            time_scale: 1.,
+            is_paused: false,
+            unscaled_seconds: 0.,
+            modifiers: Modifiers::default(),
+            gamepads: Gamepads::new(),
+            text_input: false,
             close_requested: false,
             native_px,
             is_fullscreen: false,
             desires_fullscreen: false,
+            config: Config::default(),
             cookie: Vec::new(),
             cookie_updated: false,
         }
     }
 
     pub(crate) fn set_cursor(&mut self, cursor: (f64, f64)) {
+        let previous = self.cursor;
         self.cursor = cursor;
         self.bound_cursor();
+        self.cursor_delta.0 += self.cursor.0 - previous.0;
+        self.cursor_delta.1 += self.cursor.1 - previous.1;
+    }
+
+    // Resets the per-frame input state, invoked by the backends after `App::advance`.
+    pub(crate) fn end_frame(&mut self) {
+        self.pressed_keys.clear();
+        self.released_keys.clear();
+        self.cursor_delta = (0., 0.);
+        self.mouse_motion = (0., 0.);
+    }
+
+    // Takes raw relative motion in native pixels, with positive y pointing down.
+    pub(crate) fn add_mouse_motion(&mut self, dx: f64, dy: f64) {
+        self.mouse_motion.0 += dx * self.native_px;
+        self.mouse_motion.1 -= dy * self.native_px;
     }
 
-    #[allow(dead_code)]
     pub(crate) fn set_touches_pos<F>(&mut self, touches: Vec<TouchPoint>, normalize_fn: F)
     where
         F: Fn(f64, f64) -> (f64, f64),
@@ -75,6 +159,33 @@ impl<A: AppAssetId> AppContext<A> {
         self.bound_cursor();
     }
 
+    // Returns false if the key was already held down.
+    pub(crate) fn press_key(&mut self, key: KeyCode) -> bool {
+        let pressed = self.held_keys.insert(key);
+        if pressed {
+            self.pressed_keys.insert(key);
+        }
+        self.modifiers = Modifiers::from_held_keys(&self.held_keys);
+        pressed
+    }
+
+    // Returns false if the key was not held down.
+    pub(crate) fn release_key(&mut self, key: KeyCode) -> bool {
+        let released = self.held_keys.remove(&key);
+        if released {
+            self.released_keys.insert(key);
+        }
+        self.modifiers = Modifiers::from_held_keys(&self.held_keys);
+        released
+    }
+
+    pub(crate) fn release_all_keys(&mut self) -> Vec<KeyCode> {
+        self.modifiers = Modifiers::default();
+        let keys: Vec<KeyCode> = self.held_keys.drain().collect();
+        self.released_keys.extend(keys.iter().cloned());
+        keys
+    }
+
     pub(crate) fn set_dims(&mut self, dims: (f64, f64), native_px: f64) {
         self.dims = dims;
         self.native_px = native_px;
@@ -102,6 +213,105 @@ impl<A: AppAssetId> AppContext<A> {
         self.cursor
     }
 
+    /// Returns how far the mouse cursor moved since the previous frame, in app coordinates.
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// Returns the relative mouse movement since the previous frame, in app coordinates.
+    ///
+    /// Unlike `cursor_delta()`, this keeps reporting movement when the cursor is stopped
+    /// by the edges of the window or locked with `request_pointer_lock()`, which makes it
+    /// suitable for mouse-look or drag-to-pan controls.
+    pub fn mouse_motion(&self) -> (f64, f64) {
+        self.mouse_motion
+    }
+
+    /// Shows or hides the operating system mouse cursor over the app, visible by default.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    /// Checks whether the mouse cursor is visible, see `set_cursor_visible`.
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Replaces the operating system mouse cursor with a sprite, or restores it with `None`.
+    ///
+    /// The sprite is drawn on top of everything else, with its origin at `cursor()`,
+    /// so the sprite origin acts as the cursor hotspot. It is not drawn while the cursor
+    /// is hidden or the pointer is locked.
+    pub fn set_cursor_sprite(&mut self, sprite: Option<A::Sprite>) {
+        self.cursor_sprite = sprite.map(|sprite| sprite.id_u16());
+    }
+
+    pub(crate) fn cursor_sprite_id(&self) -> Option<u16> {
+        if self.cursor_visible && !self.is_pointer_locked {
+            self.cursor_sprite
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn shows_os_cursor(&self) -> bool {
+        self.cursor_visible && self.cursor_sprite.is_none() && !self.is_pointer_locked
+    }
+
+    /// Requests the mouse pointer to be locked to the app, hiding it.
+    ///
+    /// While locked, `cursor()` no longer changes and mouse movement is only reported
+    /// through `mouse_motion()`. When running natively the cursor is grabbed by the window.
+    /// When compiling to `wasm32-unknown-unknown`, this uses the browser Pointer Lock API,
+    /// which only succeeds during user input events (see `request_fullscreen`), and the
+    /// user can leave the lock at any time by pressing Escape.
+    pub fn request_pointer_lock(&mut self) {
+        self.desires_pointer_lock = true;
+    }
+
+    /// Requests the mouse pointer to be released from the app.
+    pub fn release_pointer_lock(&mut self) {
+        self.desires_pointer_lock = false;
+    }
+
+    /// Checks whether or not the mouse pointer is currently locked.
+    ///
+    /// This value will not change immediately after a call to `request_pointer_lock` or
+    /// `release_pointer_lock`.
+    pub fn is_pointer_locked(&self) -> bool {
+        self.is_pointer_locked
+    }
+
+    pub(crate) fn desires_pointer_lock(&self) -> bool {
+        self.desires_pointer_lock
+    }
+
+    pub(crate) fn set_is_pointer_locked(&mut self, is_pointer_locked: bool) {
+        self.is_pointer_locked = is_pointer_locked;
+        self.desires_pointer_lock = is_pointer_locked;
+    }
+
+    /// Checks whether a key or mouse button is currently held down.
+    ///
+    /// This polled state is an alternative to tracking `App::key_down` and `App::key_up`.
+    /// `KeyCode::Touch` is not tracked, use `touches()` instead.
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.held_keys.contains(&key)
+    }
+
+    /// Checks whether a key or mouse button was pressed since the previous frame.
+    ///
+    /// This stays true for the whole `App::advance` call, even if the key was
+    /// released again before the frame started.
+    pub fn was_pressed_this_frame(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    /// Checks whether a key or mouse button was released since the previous frame.
+    pub fn was_released_this_frame(&self, key: KeyCode) -> bool {
+        self.released_keys.contains(&key)
+    }
+
     /// Returns the touches points `TouchPoint` positions in app coordinates.
     ///
     /// The x coordinate lies in the range `0` to `self.dims().0`.
@@ -110,6 +320,62 @@ impl<A: AppAssetId> AppContext<A> {
         &self.touches
     }
 
+    /// Returns which modifier keys (Shift, Control, Alt, Meta) are currently held down.
+    ///
+    /// This is already up to date when `App::key_down` or `App::key_up` is invoked
+    /// for a modifier key.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Returns the currently connected gamepads, in the order they were connected.
+    pub fn gamepads(&self) -> &[Gamepad] {
+        self.gamepads.all()
+    }
+
+    /// Returns the gamepad with the given id, or `None` if it has been disconnected.
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(id)
+    }
+
+    /// Sets the deadzone applied to gamepad stick axes, defaults to `0.15`.
+    ///
+    /// Stick values closer than this to the center read as zero.
+    pub fn set_gamepad_deadzone(&mut self, deadzone: f32) {
+        assert!(
+            (0. ..1.).contains(&deadzone),
+            "unrealistic gamepad deadzone: {}",
+            deadzone
+        );
+        self.gamepads.set_deadzone(deadzone);
+    }
+
+    /// Starts delivering typed text to `App::text_input`.
+    ///
+    /// Use this for things like name entry or chat, where the actual characters matter
+    /// rather than the physical keys; dead keys and input methods (IME) are handled by
+    /// the platform. Key presses are still delivered to `App::key_down` as usual, which
+    /// is how Backspace or Return should be handled.
+    ///
+    /// When compiling to `wasm32-unknown-unknown`, this also brings up the on-screen
+    /// keyboard on mobile browsers, though browsers may only allow that during
+    /// user input events.
+    pub fn start_text_input(&mut self) {
+        self.text_input = true;
+    }
+
+    /// Stops delivering typed text to `App::text_input`.
+    pub fn stop_text_input(&mut self) {
+        self.text_input = false;
+    }
+
+    /// Checks whether text input is currently active.
+    ///
+    /// See `start_text_input` and `stop_text_input`.
+    pub fn is_text_input_active(&self) -> bool {
+        self.text_input
+    }
+
     /// Returns the width of a native pixel, measured in "app pixels".
     ///
     /// This value will always be at most 1.
@@ -161,6 +427,97 @@ impl<A: AppAssetId> AppContext<A> {
         self.desires_fullscreen = is_fullscreen;
     }
 
+    /// Checks whether the app currently has keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    /// Checks whether the app is currently suspended, i.e. not visible.
+    ///
+    /// See `App::suspended`.
+    pub fn is_suspended(&self) -> bool {
+        self.is_suspended
+    }
+
+    pub(crate) fn set_focused(&mut self, focused: bool) {
+        self.is_focused = focused;
+        self.update_music_pause();
+    }
+
+    pub(crate) fn set_suspended(&mut self, suspended: bool) {
+        self.is_suspended = suspended;
+        self.update_music_pause();
+    }
+
+    pub(crate) fn set_pause_when_inactive(&mut self, pause_when_inactive: bool) {
+        self.pause_when_inactive = pause_when_inactive;
+    }
+
+    // Whether `App::advance` is currently skipped, see `AppInfo::pause_when_inactive`.
+    pub(crate) fn is_auto_paused(&self) -> bool {
+        self.pause_when_inactive && (!self.is_focused || self.is_suspended)
+    }
+
+    /// Returns the factor applied to the `seconds` passed to `App::advance`.
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets the factor applied to the `seconds` passed to `App::advance` and to the
+    /// timers and tweens in `timers` (default is `1.0`), e.g. `0.25` for slow motion.
+    ///
+    /// The `ui_timers` and `unscaled_seconds` are not affected.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    /// Freezes the game time until `resume` is invoked, e.g. for a pause menu or hit-stop.
+    ///
+    /// `App::advance` is still invoked every frame, but with `0.0` seconds, and the timers
+    /// and tweens in `timers` stop advancing. The `ui_timers` and `unscaled_seconds` are not affected.
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    /// Resumes the game time frozen with `pause`.
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+    }
+
+    /// Checks whether the game time was frozen with `pause`.
+    ///
+    /// This does not include the automatic pause when the app is inactive, see
+    /// `AppInfo::pause_when_inactive`, during which `App::advance` is not invoked at all.
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Returns the real time elapsed during the current frame, ignoring the time scale
+    /// and `pause`.
+    pub fn unscaled_seconds(&self) -> f64 {
+        self.unscaled_seconds
+    }
+
+    // Advances the clocks by the real `elapsed` time, returning the seconds to pass to
+    // `App::advance`.
+    pub(crate) fn advance_clocks(&mut self, elapsed: f64) -> f64 {
+        let seconds = if self.is_paused {
+            0.
+        } else {
+            elapsed * self.time_scale
+        };
+        self.unscaled_seconds = elapsed;
+        // both sets follow the same path, only the clock driving them differs
+        self.ui_timers.advance(elapsed);
+        self.timers.advance(seconds);
+        seconds
+    }
+
+    fn update_music_pause(&mut self) {
+        let paused = self.is_auto_paused();
+        self.audio.set_music_paused(paused);
+    }
+
     /// Closes the app entirely.
     ///
     /// When compiling to `wasm32-unknown-unknown`, the app may be resumed after it is closed
@@ -175,20 +532,91 @@ impl<A: AppAssetId> AppContext<A> {
         result
     }
 
+    /// Returns the user preferences, see the `config` module.
+    ///
+    /// This is the config loaded when the app started, or the last one passed to
+    /// `set_config`.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Replaces the user preferences, e.g. from an options menu.
+    ///
+    /// The audio volumes and fullscreen mode that are set are applied immediately (in a web
+    /// browser, fullscreen can only be entered during user input events, see
+    /// `request_fullscreen`), those left unset keep their current value.
+    /// The window size and vertical synchronization take effect the next time the app starts.
+    /// Key bindings are not applied automatically, see `Config::apply_bindings`.
+    /// Use `save_config` to persist the config.
+    pub fn set_config(&mut self, config: Config) {
+        if let Some(volume) = config.audio.master_volume {
+            self.audio.set_master_volume(volume);
+        }
+        if let Some(volume) = config.audio.music_volume {
+            self.audio.set_music_volume(volume);
+        }
+        if let Some(volume) = config.audio.sound_volume {
+            self.audio.set_sound_volume(volume);
+        }
+        match config.window.fullscreen {
+            Some(true) => self.request_fullscreen(),
+            Some(false) => self.cancel_fullscreen(),
+            None => {}
+        }
+        self.config = config;
+    }
+
+    /// Writes the current config (see `set_config`) to the user config file,
+    /// so it is loaded the next time the app starts.
+    pub fn save_config(&mut self) -> Result<(), StorageError> {
+        let text = self.config.to_toml()?;
+        self.storage.write_config(&text)
+    }
+
+    /// Serializes `value` and writes it to the storage `slot`.
+    ///
+    /// See `Storage::save`.
+    pub fn save<T: Serialize>(&mut self, slot: &str, value: &T) -> Result<(), StorageError> {
+        self.storage.save(slot, value)
+    }
+
+    /// Reads a value written by `AppContext::save`, or `None` if the storage `slot` is empty.
+    ///
+    /// See `Storage::load`.
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> Result<Option<T>, StorageError> {
+        self.storage.load(slot)
+    }
+
+    /// Reads a value written by `AppContext::save`, converting values saved with an older
+    /// schema version through `migrate`.
+    ///
+    /// See `Storage::load_migrating`.
+    pub fn load_migrating<T, F>(&self, slot: &str, migrate: F) -> Result<Option<T>, StorageError>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(u32, &[u8]) -> Result<T, StorageError>,
+    {
+        self.storage.load_migrating(slot, migrate)
+    }
+
     /// Gets current cookie data.
     ///
-    /// NOTE: this API is likely to change change.
+    /// Deprecated in favor of `AppContext::storage`, which persists data of any size
+    /// on every target.
     /// Returns an empty array if cookie is not set or if not running in WebAssembly mode.
+    #[deprecated(note = "use `AppContext::storage` instead")]
     pub fn cookie(&self) -> &[u8] {
         &self.cookie
     }
 
-    /// Writes cookie data.
+    /// Writes cookie data, which must be less than 700 bytes long.
     ///
-    /// NOTE: this API is likely to change change.
+    /// Deprecated in favor of `AppContext::storage`, which persists data of any size
+    /// on every target.
     /// Cookie can be used as lightweight save data when built in WebAssembly mode.
     /// Only writes persistent cookie data if built in WebAssembly mode.
     /// To use cookies, the readCookie and writeCookie functions must be passed into nuuro.js.
+    #[deprecated(note = "use `AppContext::storage` instead")]
     pub fn set_cookie(&mut self, cookie: Vec<u8>) {
         assert!(cookie.len() < 700);
         if cookie != self.cookie {
@@ -211,8 +639,16 @@ impl<A: AppAssetId> AppContext<A> {
 }
 
 /// Struct for audio playback.
+///
+/// The volume passed when playing a sound or music is multiplied by the master volume
+/// and the sound or music volume, which are meant to be controlled by the user
+/// (see `AppContext::set_config`).
 pub struct Audio<A: AppAssetId> {
     core: CoreAudio,
+    master_volume: f32,
+    music_volume: f32,
+    sound_volume: f32,
+    music_base_volume: f32,
     phantom: PhantomData<A>,
 }
 
@@ -222,22 +658,48 @@ impl<A: AppAssetId> Audio<A> {
     /// The volume value `1.0` is the "normal" volume (unfiltered input).
     /// Any value other than `1.0` will multiply each sample by this value.
     pub fn play_sound(&mut self, sound: A::Sound, volume: f32) {
+        let volume = volume * self.master_volume * self.sound_volume;
         self.core.play_sound(sound.id_u16(), volume);
     }
 
+    /// Generates a sound effect from the given `params` and registers it for playback.
+    ///
+    /// Generation happens immediately, so sounds are best registered once
+    /// (e.g. in `App::start`) and played many times with `play_synth_sound`.
+    pub fn add_synth_sound(&mut self, params: &SynthParams) -> SynthSoundId {
+        SynthSoundId(self.core.add_synth_sound(params.generate(), SAMPLE_RATE))
+    }
+
+    /// Plays the given synthesized sound effect once.
+    ///
+    /// The volume value `1.0` is the "normal" volume (unfiltered input).
+    /// Any value other than `1.0` will multiply each sample by this value.
+    pub fn play_synth_sound(&mut self, sound: SynthSoundId, volume: f32) {
+        let volume = volume * self.master_volume * self.sound_volume;
+        self.core.play_synth_sound(sound.0, volume);
+    }
+
     /// Plays the given music once, replacing the currently playing music, if any.
     ///
     /// The volume value `1.0` is the "normal" volume (unfiltered input).
     /// Any value other than `1.0` will multiply each sample by this value.
     pub fn play_music(&mut self, music: A::Music, volume: f32) {
+        self.music_base_volume = volume;
+        let volume = volume * self.master_volume * self.music_volume;
         self.core.play_music(music.id_u16(), volume, false);
     }
 
     /// Continually loops the given music, replacing the currently playing music, if any.
     ///
+    /// If loop points were packed with the music (see `nuuro_build::AssetPacker::music`),
+    /// the start of the track is played once and then only the looped section repeats.
+    /// In WebAssembly mode, there may be a short gap between the start and the looped section.
+    ///
     /// The volume value `1.0` is the "normal" volume (unfiltered input).
     /// Any value other than `1.0` will multiply each sample by this value.
     pub fn loop_music(&mut self, music: A::Music, volume: f32) {
+        self.music_base_volume = volume;
+        let volume = volume * self.master_volume * self.music_volume;
         self.core.play_music(music.id_u16(), volume, true);
     }
 
@@ -245,4 +707,71 @@ impl<A: AppAssetId> Audio<A> {
     pub fn stop_music(&mut self) {
         self.core.stop_music();
     }
+
+    /// Checks whether audio can be played.
+    ///
+    /// When no audio output device is available, or the device is lost while the app runs,
+    /// all audio is silently skipped until a device becomes available again, at which point
+    /// the current music starts over.
+    pub fn is_available(&self) -> bool {
+        self.core.is_available()
+    }
+
+    /// Returns the volume that all sounds and music are multiplied by (default is `1.0`).
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    /// Sets the volume that all sounds and music are multiplied by.
+    ///
+    /// Takes effect immediately, including for the currently playing music.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.max(0.);
+        self.update_music_volume();
+    }
+
+    /// Returns the volume that music is multiplied by (default is `1.0`).
+    pub fn music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    /// Sets the volume that music is multiplied by.
+    ///
+    /// Takes effect immediately, including for the currently playing music.
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.max(0.);
+        self.update_music_volume();
+    }
+
+    /// Returns the volume that sound effects, including synthesized sounds,
+    /// are multiplied by (default is `1.0`).
+    pub fn sound_volume(&self) -> f32 {
+        self.sound_volume
+    }
+
+    /// Sets the volume that sound effects, including synthesized sounds, are multiplied by.
+    ///
+    /// Sounds that are already playing keep their volume.
+    pub fn set_sound_volume(&mut self, volume: f32) {
+        self.sound_volume = volume.max(0.);
+    }
+
+    fn update_music_volume(&mut self) {
+        let volume = self.music_base_volume * self.master_volume * self.music_volume;
+        self.core.set_music_volume(volume);
+    }
+
+    pub(crate) fn set_music_paused(&mut self, paused: bool) {
+        self.core.set_music_paused(paused);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn check_output(&mut self) {
+        self.core.check_output();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn take_music_finished(&mut self) -> bool {
+        self.core.take_music_finished()
+    }
 }
@@ -15,6 +15,8 @@
 //! Contains `AppInfo` (and related structs), a struct for specifying intialization
 //! information for running an `App`.
 
+use crate::storage;
+
 /// A struct for specifying initialization information for running an `App`.
 ///
 /// Methods for setting fields in `AppInfo` are intended to be chained together like
@@ -42,8 +44,13 @@ pub struct AppInfo {
     pub(crate) title: &'static str,
     pub(crate) target_fps: f64,
     pub(crate) resizable: bool,
+    pub(crate) fullscreen: bool,
+    pub(crate) vsync: bool,
     pub(crate) print_workload_info: bool,
     pub(crate) print_gl_info: bool,
+    pub(crate) pause_when_inactive: bool,
+    pub(crate) storage_name: Option<&'static str>,
+    pub(crate) save_schema_version: u32,
 }
 
 impl AppInfo {
@@ -72,9 +79,14 @@ impl AppInfo {
             tile_width: None,
             title: "untitled app",
             resizable: true,
+            fullscreen: false,
+            vsync: false,
             target_fps: 60.,
             print_workload_info: false,
             print_gl_info: false,
+            pause_when_inactive: false,
+            storage_name: None,
+            save_schema_version: 0,
         }
     }
 
@@ -111,6 +123,42 @@ impl AppInfo {
         self
     }
 
+    /// Specifies the name under which save data is stored (see `AppContext::storage`).
+    ///
+    /// It is used as the directory name inside the user data directory on desktop targets,
+    /// and as the `localStorage` key prefix in the browser. It should only contain ASCII
+    /// letters, digits, `-` and `_`, and should not change between releases of the app.
+    /// Defaults to a name derived from the title, e.g. `"my_game"` for `"My Game"`.
+    pub fn storage_name(mut self, storage_name: &'static str) -> Self {
+        assert!(
+            !storage_name.is_empty()
+                && storage_name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "invalid storage_name: {:?}",
+            storage_name
+        );
+        self.storage_name = Some(storage_name);
+        self
+    }
+
+    /// Specifies the schema version that values saved with `AppContext::save` are tagged
+    /// with (default is `0`).
+    ///
+    /// Increase it whenever the layout of saved types changes, and convert older saves
+    /// with `AppContext::load_migrating`.
+    pub fn save_schema_version(mut self, version: u32) -> Self {
+        self.save_schema_version = version;
+        self
+    }
+
+    pub(crate) fn effective_storage_name(&self) -> String {
+        match self.storage_name {
+            Some(name) => name.to_string(),
+            None => storage::default_storage_name(self.title),
+        }
+    }
+
     /// Specifies the intial native width and height of the window (default is `800` by `600`).
     pub fn native_dims(mut self, width: u32, height: u32) -> Self {
         assert!(
@@ -133,6 +181,22 @@ impl AppInfo {
         self
     }
 
+    /// If invoked, the app requests fullscreen mode when it starts.
+    ///
+    /// In a web browser fullscreen can only be entered during user input events,
+    /// so this only has an effect on desktop targets.
+    pub fn fullscreen(mut self) -> Self {
+        self.fullscreen = true;
+        self
+    }
+
+    /// If invoked, buffer swaps are synchronized with the display refresh rate
+    /// (only on desktop targets, browsers always synchronize).
+    pub fn vsync(mut self) -> Self {
+        self.vsync = true;
+        self
+    }
+
     /// Specifies the target frames-per-second (default is `60.`).
     pub fn target_fps(mut self, target_fps: f64) -> Self {
         assert!(
@@ -144,6 +208,13 @@ impl AppInfo {
         self
     }
 
+    /// If invoked, `App::advance` is not called and music is paused while the app
+    /// is unfocused or suspended (see `App::focus_changed` and `App::suspended`).
+    pub fn pause_when_inactive(mut self) -> Self {
+        self.pause_when_inactive = true;
+        self
+    }
+
     /// If invoked, workload info will be printed to standard output periodically.
     pub fn print_workload_info(mut self) -> Self {
         self.print_workload_info = true;
@@ -0,0 +1,1044 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Collision detection for 2D games.
+//!
+//! Shapes are expressed in app pixels, with the y axis pointing up like in the renderer.
+//! `Shape::contact` tests two shapes against each other, returning how to separate them,
+//! and `Segment::cast` finds where a moving point or a ray first hits a shape.
+//!
+//! Testing every pair of objects quickly gets slow, so a `SpatialHash` can be used to only
+//! test the objects close to each other. Levels made of tiles are better handled by a
+//! `TileGrid`, moving boxes while stopping them against solid tiles.
+//!
+//! ```
+//! use nuuro::collision::{Aabb, Circle, Shape, SpatialHash};
+//! use nuuro::Vec2;
+//!
+//! let mut hash = SpatialHash::new(32.);
+//! let player = Shape::from(Circle::new(Vec2::new(10., 10.), 4.));
+//! let crate_box = Shape::from(Aabb::new(Vec2::new(12., 0.), Vec2::new(20., 8.)));
+//! hash.insert(1, crate_box.bounds());
+//!
+//! for id in hash.query(&player.bounds()) {
+//!     if let Some(contact) = player.contact(&crate_box) {
+//!         // moving the player by `normal * depth` separates it from the box
+//!         assert_eq!(id, 1);
+//!         assert!(contact.normal.x < 0. && contact.depth > 0.);
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::renderer::{Affine, Vec2};
+
+// Tolerance used to treat shapes that are merely touching as not overlapping
+const EPSILON: f64 = 1e-9;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    /// Corner with the lowest coordinates.
+    pub min: Vec2,
+    /// Corner with the highest coordinates.
+    pub max: Vec2,
+}
+
+impl Aabb {
+    /// Returns the box spanning between two opposite corners.
+    pub fn new(a: Vec2, b: Vec2) -> Aabb {
+        Aabb {
+            min: Vec2::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Vec2::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    /// Returns the box centered at `center`, extending `half_width` and `half_height`
+    /// on each side.
+    pub fn from_center(center: Vec2, half_width: f64, half_height: f64) -> Aabb {
+        let half = Vec2::new(half_width.abs(), half_height.abs());
+        Aabb {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    /// Returns the center of the box.
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the width of the box.
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+
+    /// Returns the height of the box.
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+
+    /// Returns the box moved by `offset`.
+    pub fn translate(&self, offset: Vec2) -> Aabb {
+        Aabb {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Returns the smallest box containing both `self` and `other`.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Vec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+
+    /// Checks whether `point` is inside the box or on its border.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    /// Checks whether the boxes overlap, boxes merely touching do not.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+    }
+
+    // Corners in counter-clockwise order
+    fn corners(&self) -> Vec<Vec2> {
+        vec![
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ]
+    }
+}
+
+/// Circle, e.g. for round objects or distance checks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f64,
+}
+
+impl Circle {
+    /// Returns the circle centered at `center`.
+    pub fn new(center: Vec2, radius: f64) -> Circle {
+        Circle {
+            center,
+            radius: radius.abs(),
+        }
+    }
+
+    /// Returns the bounding box of the circle.
+    pub fn bounds(&self) -> Aabb {
+        Aabb::from_center(self.center, self.radius, self.radius)
+    }
+
+    /// Checks whether `point` is inside the circle or on its border.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        (point - self.center).len() <= self.radius
+    }
+}
+
+/// Convex polygon, e.g. for slopes or rotated boxes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    points: Vec<Vec2>,
+}
+
+impl Polygon {
+    /// Returns the polygon with the given vertices, which must describe a convex shape.
+    ///
+    /// The vertices may be given in either order, they are stored counter-clockwise.
+    ///
+    /// Panics if there are less than 3 vertices.
+    pub fn new(mut points: Vec<Vec2>) -> Polygon {
+        assert!(points.len() >= 3, "a polygon needs at least 3 points");
+        let doubled_area: f64 = (0..points.len())
+            .map(|i| points[i].cross(points[(i + 1) % points.len()]))
+            .sum();
+        if doubled_area < 0. {
+            points.reverse();
+        }
+        Polygon { points }
+    }
+
+    /// Returns the polygon covering the same area as `aabb`.
+    pub fn from_aabb(aabb: &Aabb) -> Polygon {
+        Polygon {
+            points: aabb.corners(),
+        }
+    }
+
+    /// Returns the vertices, in counter-clockwise order.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// Returns the polygon with every vertex transformed by `affine`,
+    /// e.g. the `ecs::Transform::affine` of an entity.
+    pub fn transform(&self, affine: &Affine) -> Polygon {
+        Polygon::new(self.points.iter().map(|&p| affine.apply(p)).collect())
+    }
+
+    /// Returns the bounding box of the polygon.
+    pub fn bounds(&self) -> Aabb {
+        bounds_of(&self.points)
+    }
+
+    /// Checks whether `point` is inside the polygon or on its border.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        edges(&self.points).all(|(start, normal)| normal.dot(point - start) <= EPSILON)
+    }
+}
+
+/// Line segment between two points, also used as a ray or as the path of a moving point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Segment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+/// Result of casting a `Segment` against a shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Hit {
+    /// Position of the hit along the segment, from `0.0` at its start to `1.0` at its end.
+    pub time: f64,
+    /// Point where the segment first touches the shape.
+    pub point: Vec2,
+    /// Unit normal of the surface that was hit.
+    pub normal: Vec2,
+}
+
+impl Segment {
+    /// Returns the segment going from `start` to `end`.
+    pub fn new(start: Vec2, end: Vec2) -> Segment {
+        Segment { start, end }
+    }
+
+    /// Returns the length of the segment.
+    pub fn len(&self) -> f64 {
+        (self.end - self.start).len()
+    }
+
+    /// Returns the bounding box of the segment.
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.start, self.end)
+    }
+
+    /// Returns the point of the segment closest to `point`.
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let dir = self.end - self.start;
+        let len_sq = dir.dot(dir);
+        if len_sq == 0. {
+            return self.start;
+        }
+        let t = ((point - self.start).dot(dir) / len_sq).clamp(0., 1.);
+        self.start + dir * t
+    }
+
+    /// Returns the point where the segments cross, or `None` if they do not.
+    ///
+    /// Parallel segments are never considered crossing, even when overlapping.
+    pub fn intersection(&self, other: &Segment) -> Option<Vec2> {
+        let r = self.end - self.start;
+        let s = other.end - other.start;
+        let denom = r.cross(s);
+        if denom.abs() < EPSILON {
+            return None;
+        }
+        let offset = other.start - self.start;
+        let t = offset.cross(s) / denom;
+        let u = offset.cross(r) / denom;
+        if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+            Some(self.start + r * t)
+        } else {
+            None
+        }
+    }
+
+    /// Finds where the segment, going from `start` to `end`, first enters `shape`.
+    ///
+    /// If `start` is already inside the shape, the hit is at time `0.0` with a normal
+    /// pointing back along the segment.
+    pub fn cast(&self, shape: &Shape) -> Option<Hit> {
+        match shape {
+            Shape::Circle(circle) => self.cast_circle(circle),
+            Shape::Aabb(aabb) => self.cast_convex(&aabb.corners()),
+            Shape::Polygon(polygon) => self.cast_convex(&polygon.points),
+        }
+    }
+
+    fn backwards_normal(&self) -> Vec2 {
+        normalize(self.start - self.end).unwrap_or_else(Vec2::zero)
+    }
+
+    fn cast_circle(&self, circle: &Circle) -> Option<Hit> {
+        let dir = self.end - self.start;
+        let from_center = self.start - circle.center;
+        let c = from_center.dot(from_center) - circle.radius * circle.radius;
+        if c <= 0. {
+            return Some(Hit {
+                time: 0.,
+                point: self.start,
+                normal: normalize(from_center).unwrap_or_else(|| self.backwards_normal()),
+            });
+        }
+        let a = dir.dot(dir);
+        let b = 2. * from_center.dot(dir);
+        let discriminant = b * b - 4. * a * c;
+        if a == 0. || discriminant < 0. {
+            return None;
+        }
+        let time = (-b - discriminant.sqrt()) / (2. * a);
+        if !(0. ..=1.).contains(&time) {
+            return None;
+        }
+        let point = self.start + dir * time;
+        Some(Hit {
+            time,
+            point,
+            normal: normalize(point - circle.center).unwrap_or_else(|| self.backwards_normal()),
+        })
+    }
+
+    // Cyrus-Beck clipping against each edge of a counter-clockwise convex polygon
+    fn cast_convex(&self, points: &[Vec2]) -> Option<Hit> {
+        let dir = self.end - self.start;
+        let (mut enter, mut exit) = (0., 1.);
+        let mut enter_normal = None;
+        for (edge_start, normal) in edges(points) {
+            let num = normal.dot(edge_start - self.start);
+            let den = normal.dot(dir);
+            if den == 0. {
+                if num < 0. {
+                    return None;
+                }
+            } else if den < 0. {
+                let time = num / den;
+                if time > enter {
+                    enter = time;
+                    enter_normal = Some(normal);
+                }
+            } else {
+                exit = f64::min(exit, num / den);
+            }
+            if enter > exit {
+                return None;
+            }
+        }
+        Some(Hit {
+            time: enter,
+            point: self.start + dir * enter,
+            normal: enter_normal.unwrap_or_else(|| self.backwards_normal()),
+        })
+    }
+}
+
+/// Result of testing two overlapping shapes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Contact {
+    /// Unit vector along which to move the first shape to separate it from the second.
+    pub normal: Vec2,
+    /// Distance to move the first shape along `normal` to separate the shapes.
+    pub depth: f64,
+}
+
+impl Contact {
+    fn flip(self) -> Contact {
+        Contact {
+            normal: -self.normal,
+            depth: self.depth,
+        }
+    }
+}
+
+/// Any shape that can be tested against the others.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Aabb(Aabb),
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+impl From<Aabb> for Shape {
+    fn from(aabb: Aabb) -> Shape {
+        Shape::Aabb(aabb)
+    }
+}
+
+impl From<Circle> for Shape {
+    fn from(circle: Circle) -> Shape {
+        Shape::Circle(circle)
+    }
+}
+
+impl From<Polygon> for Shape {
+    fn from(polygon: Polygon) -> Shape {
+        Shape::Polygon(polygon)
+    }
+}
+
+impl Shape {
+    /// Returns the bounding box of the shape, e.g. to insert it in a `SpatialHash`.
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            Shape::Aabb(aabb) => *aabb,
+            Shape::Circle(circle) => circle.bounds(),
+            Shape::Polygon(polygon) => polygon.bounds(),
+        }
+    }
+
+    /// Checks whether `point` is inside the shape or on its border.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        match self {
+            Shape::Aabb(aabb) => aabb.contains_point(point),
+            Shape::Circle(circle) => circle.contains_point(point),
+            Shape::Polygon(polygon) => polygon.contains_point(point),
+        }
+    }
+
+    /// Checks whether the shapes overlap, shapes merely touching do not.
+    pub fn intersects(&self, other: &Shape) -> bool {
+        self.contact(other).is_some()
+    }
+
+    /// Tests the shapes against each other, returning how to separate them if they overlap.
+    ///
+    /// Polygons are tested with the separating axis theorem.
+    pub fn contact(&self, other: &Shape) -> Option<Contact> {
+        match (self, other) {
+            (Shape::Aabb(a), Shape::Aabb(b)) => aabb_contact(a, b),
+            (Shape::Circle(a), Shape::Circle(b)) => circle_contact(a, b),
+            (Shape::Circle(a), b) => circle_polygon_contact(a, &b.vertices()),
+            (a, Shape::Circle(b)) => circle_polygon_contact(b, &a.vertices()).map(Contact::flip),
+            (a, b) => polygon_contact(&a.vertices(), &b.vertices()),
+        }
+    }
+
+    fn vertices(&self) -> Vec<Vec2> {
+        match self {
+            Shape::Aabb(aabb) => aabb.corners(),
+            Shape::Circle(_) => unreachable!(),
+            Shape::Polygon(polygon) => polygon.points.clone(),
+        }
+    }
+}
+
+// Unlike `Vec2::normalize`, treats nearly zero vectors as having no direction
+fn normalize(v: Vec2) -> Option<Vec2> {
+    if v.len() > EPSILON {
+        Some(v.normalize())
+    } else {
+        None
+    }
+}
+
+fn bounds_of(points: &[Vec2]) -> Aabb {
+    points[1..]
+        .iter()
+        .fold(Aabb::new(points[0], points[0]), |bounds, &p| {
+            bounds.union(&Aabb::new(p, p))
+        })
+}
+
+// Start point and outward unit normal of each edge of a counter-clockwise polygon
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..points.len()).filter_map(move |i| {
+        let start = points[i];
+        let edge = points[(i + 1) % points.len()] - start;
+        normalize(Vec2::new(edge.y, -edge.x)).map(|normal| (start, normal))
+    })
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f64, f64) {
+    points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| {
+        let d = p.dot(axis);
+        (min.min(d), max.max(d))
+    })
+}
+
+// Finds the shortest way to separate `a` from `b` along the given axes, if they overlap
+// along all of them.
+fn separating_axis_contact(
+    axes: impl Iterator<Item = Vec2>,
+    a: impl Fn(Vec2) -> (f64, f64),
+    b: impl Fn(Vec2) -> (f64, f64),
+) -> Option<Contact> {
+    let mut best: Option<Contact> = None;
+    for axis in axes {
+        let (a_min, a_max) = a(axis);
+        let (b_min, b_max) = b(axis);
+        let (push_back, push_forward) = (a_max - b_min, b_max - a_min);
+        let depth = push_back.min(push_forward);
+        if depth <= EPSILON {
+            return None;
+        }
+        let shallower = match best {
+            Some(best) => depth < best.depth,
+            None => true,
+        };
+        if shallower {
+            let normal = if push_back < push_forward {
+                -axis
+            } else {
+                axis
+            };
+            best = Some(Contact { normal, depth });
+        }
+    }
+    best
+}
+
+fn aabb_contact(a: &Aabb, b: &Aabb) -> Option<Contact> {
+    separating_axis_contact(
+        [Vec2::new(1., 0.), Vec2::new(0., 1.)].iter().copied(),
+        |axis| project(&[a.min, a.max], axis),
+        |axis| project(&[b.min, b.max], axis),
+    )
+}
+
+fn circle_contact(a: &Circle, b: &Circle) -> Option<Contact> {
+    let offset = a.center - b.center;
+    let depth = a.radius + b.radius - offset.len();
+    if depth <= EPSILON {
+        return None;
+    }
+    Some(Contact {
+        normal: normalize(offset).unwrap_or_else(|| Vec2::new(0., 1.)),
+        depth,
+    })
+}
+
+fn circle_polygon_contact(circle: &Circle, points: &[Vec2]) -> Option<Contact> {
+    // besides the edge normals, the axis towards the closest vertex handles the corners
+    let closest = points.iter().copied().min_by(|p, q| {
+        let (p, q) = ((*p - circle.center).len(), (*q - circle.center).len());
+        p.total_cmp(&q)
+    })?;
+    // any axis is fine when the center is on the vertex, which also covers point polygons
+    let corner_axis = normalize(circle.center - closest).unwrap_or_else(|| Vec2::new(0., 1.));
+    separating_axis_contact(
+        edges(points)
+            .map(|(_, normal)| normal)
+            .chain(std::iter::once(corner_axis)),
+        |axis| {
+            let center = circle.center.dot(axis);
+            (center - circle.radius, center + circle.radius)
+        },
+        |axis| project(points, axis),
+    )
+}
+
+fn polygon_contact(a: &[Vec2], b: &[Vec2]) -> Option<Contact> {
+    separating_axis_contact(
+        edges(a).chain(edges(b)).map(|(_, normal)| normal),
+        |axis| project(a, axis),
+        |axis| project(b, axis),
+    )
+}
+
+/// Broadphase grid, finding the items whose bounding boxes are close to an area.
+///
+/// Items are stored in every square cell their bounding box overlaps, so the cell size
+/// should be around the size of a typical item.
+pub struct SpatialHash<T> {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<T>>,
+    bounds: HashMap<T, Aabb>,
+}
+
+impl<T: Copy + Eq + Hash> SpatialHash<T> {
+    /// Returns an empty hash with cells of `cell_size` app pixels.
+    ///
+    /// Panics if `cell_size` is not positive.
+    pub fn new(cell_size: f64) -> SpatialHash<T> {
+        assert!(cell_size > 0., "cell size must be positive");
+        SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    /// Inserts `item` with the given bounding box, replacing its previous one if any.
+    ///
+    /// Panics if `bounds` is not finite.
+    pub fn insert(&mut self, item: T, bounds: Aabb) {
+        assert_finite(&bounds);
+        self.remove(item);
+        for cell in self.cells_of(&bounds) {
+            self.cells.entry(cell).or_default().push(item);
+        }
+        self.bounds.insert(item, bounds);
+    }
+
+    /// Removes `item`, returning whether it was present.
+    pub fn remove(&mut self, item: T) -> bool {
+        let bounds = match self.bounds.remove(&item) {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+        for cell in self.cells_of(&bounds) {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|&other| other != item);
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the bounding box `item` was inserted with.
+    pub fn bounds(&self, item: T) -> Option<Aabb> {
+        self.bounds.get(&item).copied()
+    }
+
+    /// Returns the number of items.
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    /// Checks whether there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Removes all items.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    /// Returns the items whose bounding box overlaps `area`, each once.
+    ///
+    /// Panics if `area` is not finite.
+    pub fn query(&self, area: &Aabb) -> Vec<T> {
+        assert_finite(area);
+        let mut found = HashSet::new();
+        let mut result = Vec::new();
+        for cell in self.cells_of(area) {
+            for &item in self.cells.get(&cell).into_iter().flatten() {
+                if self.bounds[&item].intersects(area) && found.insert(item) {
+                    result.push(item);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the items whose bounding box contains `point`.
+    pub fn query_point(&self, point: Vec2) -> Vec<T> {
+        let cell = self.cell_of(point);
+        self.cells
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|item| self.bounds[item].contains_point(point))
+            .collect()
+    }
+
+    /// Returns every pair of items whose bounding boxes overlap, each pair once.
+    ///
+    /// These are the candidates to test more precisely, e.g. with `Shape::contact`.
+    pub fn pairs(&self) -> Vec<(T, T)> {
+        let mut found = HashSet::new();
+        let mut result = Vec::new();
+        for items in self.cells.values() {
+            for (i, &a) in items.iter().enumerate() {
+                for &b in &items[i + 1..] {
+                    if self.bounds[&a].intersects(&self.bounds[&b])
+                        && !found.contains(&(b, a))
+                        && found.insert((a, b))
+                    {
+                        result.push((a, b));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn cell_of(&self, point: Vec2) -> (i64, i64) {
+        (
+            (point.x / self.cell_size).floor() as i64,
+            (point.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    fn cells_of(&self, bounds: &Aabb) -> impl Iterator<Item = (i64, i64)> {
+        let (min_x, min_y) = self.cell_of(bounds.min);
+        let (max_x, max_y) = self.cell_of(bounds.max);
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+}
+
+// Infinite or NaN bounds would span an unbounded number of cells
+fn assert_finite(bounds: &Aabb) {
+    assert!(
+        [bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y]
+            .iter()
+            .all(|c| c.is_finite()),
+        "bounds must be finite"
+    );
+}
+
+/// Grid of square tiles, each solid or empty, with its bottom-left corner at `(0, 0)`.
+///
+/// Tiles outside of the grid are empty.
+///
+/// # Example
+///
+/// ```rust
+/// use nuuro::collision::{Aabb, TileGrid};
+/// use nuuro::Vec2;
+///
+/// let mut level = TileGrid::new(10, 10, 16.);
+/// for x in 0..10 {
+///     level.set_solid(x, 0, true);
+/// }
+///
+/// // a falling player lands on the floor
+/// let player = Aabb::new(Vec2::new(20., 20.), Vec2::new(30., 40.));
+/// let step = level.move_aabb(&player, Vec2::new(0., -10.));
+/// assert!(step.blocked_y);
+/// assert_eq!(step.offset, Vec2::new(0., -4.));
+/// ```
+pub struct TileGrid {
+    width: u32,
+    height: u32,
+    tile_size: f64,
+    solid: Vec<bool>,
+}
+
+/// Result of `TileGrid::move_aabb`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TileMove {
+    /// How far the box actually moved.
+    pub offset: Vec2,
+    /// Whether a solid tile stopped the horizontal movement.
+    pub blocked_x: bool,
+    /// Whether a solid tile stopped the vertical movement.
+    pub blocked_y: bool,
+}
+
+impl TileGrid {
+    /// Returns a grid of `width` by `height` empty tiles, each `tile_size` app pixels wide.
+    ///
+    /// Panics if `tile_size` is not positive.
+    pub fn new(width: u32, height: u32, tile_size: f64) -> TileGrid {
+        assert!(tile_size > 0., "tile size must be positive");
+        TileGrid {
+            width,
+            height,
+            tile_size,
+            solid: vec![false; width as usize * height as usize],
+        }
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the size of a tile in app pixels.
+    pub fn tile_size(&self) -> f64 {
+        self.tile_size
+    }
+
+    /// Makes the tile at column `x` and row `y` solid or empty.
+    ///
+    /// Panics if the tile is outside of the grid.
+    pub fn set_solid(&mut self, x: u32, y: u32, solid: bool) {
+        assert!(x < self.width && y < self.height, "tile out of bounds");
+        self.solid[(y * self.width + x) as usize] = solid;
+    }
+
+    /// Checks whether the tile at column `x` and row `y` is solid.
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as u32) < self.width
+            && (y as u32) < self.height
+            && self.solid[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    /// Returns the column and row of the tile containing `point`.
+    pub fn tile_at(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.tile_size).floor() as i32,
+            (point.y / self.tile_size).floor() as i32,
+        )
+    }
+
+    /// Returns the area covered by the tile at column `x` and row `y`.
+    pub fn tile_bounds(&self, x: i32, y: i32) -> Aabb {
+        let min = Vec2::new(x as f64, y as f64) * self.tile_size;
+        Aabb::new(min, min + Vec2::new(self.tile_size, self.tile_size))
+    }
+
+    /// Returns the column and row of each solid tile overlapping `area`.
+    pub fn solid_tiles(&self, area: &Aabb) -> Vec<(i32, i32)> {
+        let (min_x, max_x) = self.tile_range(area.min.x, area.max.x);
+        let (min_y, max_y) = self.tile_range(area.min.y, area.max.y);
+        (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_solid(x, y))
+            .collect()
+    }
+
+    /// Checks whether any solid tile overlaps `area`.
+    pub fn overlaps(&self, area: &Aabb) -> bool {
+        !self.solid_tiles(area).is_empty()
+    }
+
+    /// Moves `aabb` by `offset`, horizontally then vertically, stopping against solid tiles.
+    ///
+    /// Tiles already overlapping the box do not stop it, so it can get out of them.
+    pub fn move_aabb(&self, aabb: &Aabb, offset: Vec2) -> TileMove {
+        let rows = self.tile_range(aabb.min.y, aabb.max.y);
+        let x = self.sweep(aabb.min.x, aabb.max.x, rows, offset.x, |x, y| {
+            self.is_solid(x, y)
+        });
+        let moved = aabb.translate(Vec2::new(x.unwrap_or(offset.x), 0.));
+        let columns = self.tile_range(moved.min.x, moved.max.x);
+        let y = self.sweep(moved.min.y, moved.max.y, columns, offset.y, |y, x| {
+            self.is_solid(x, y)
+        });
+        TileMove {
+            offset: Vec2::new(x.unwrap_or(offset.x), y.unwrap_or(offset.y)),
+            blocked_x: x.is_some(),
+            blocked_y: y.is_some(),
+        }
+    }
+
+    // Range of tiles overlapping the open interval between `min` and `max` along an axis
+    fn tile_range(&self, min: f64, max: f64) -> (i32, i32) {
+        (
+            ((min + EPSILON) / self.tile_size).floor() as i32,
+            ((max - EPSILON) / self.tile_size).ceil() as i32 - 1,
+        )
+    }
+
+    // Returns the allowed movement along an axis if a solid tile is in the way, with
+    // `solid` taking the tile index along the axis first, then across it
+    fn sweep(
+        &self,
+        min: f64,
+        max: f64,
+        across: (i32, i32),
+        offset: f64,
+        solid: impl Fn(i32, i32) -> bool,
+    ) -> Option<f64> {
+        let size = self.tile_size;
+        let blocked = |tile: i32| (across.0..=across.1).any(|other| solid(tile, other));
+        if offset > 0. {
+            let first = ((max - EPSILON) / size).ceil() as i32;
+            let last = ((max + offset) / size).ceil() as i32 - 1;
+            (first..=last)
+                .find(|&tile| blocked(tile))
+                .map(|tile| (tile as f64 * size - max).max(0.))
+        } else if offset < 0. {
+            let first = ((min + EPSILON) / size).floor() as i32 - 1;
+            let last = ((min + offset) / size).floor() as i32;
+            (last..=first)
+                .rev()
+                .find(|&tile| blocked(tile))
+                .map(|tile| ((tile + 1) as f64 * size - min).min(0.))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Shape {
+        Aabb::new(Vec2::new(min_x, min_y), Vec2::new(max_x, max_y)).into()
+    }
+
+    fn circle(x: f64, y: f64, radius: f64) -> Shape {
+        Circle::new(Vec2::new(x, y), radius).into()
+    }
+
+    fn triangle(offset: Vec2) -> Shape {
+        let points = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 10.)];
+        Polygon::new(points.into_iter().map(|p| p + offset).collect()).into()
+    }
+
+    fn assert_contact(a: &Shape, b: &Shape, normal: Vec2, depth: f64) {
+        let contact = a.contact(b).expect("shapes should overlap");
+        assert!(
+            (contact.normal - normal).len() < 1e-9 && (contact.depth - depth).abs() < 1e-9,
+            "expected normal {:?} and depth {}, got {:?}",
+            normal,
+            depth,
+            contact
+        );
+        // the contact seen from the other shape is the opposite one
+        let reverse = b.contact(a).expect("shapes should overlap both ways");
+        assert!((reverse.normal + normal).len() < 1e-9);
+        assert!((reverse.depth - depth).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contact_normal_pushes_the_first_shape_out() {
+        let left = Vec2::new(-1., 0.);
+        let up = Vec2::new(0., 1.);
+        let square = aabb(0., 0., 10., 10.);
+        assert_contact(&square, &aabb(8., 2., 18., 8.), left, 2.);
+        assert_contact(&circle(0., 0., 5.), &circle(8., 0., 5.), left, 2.);
+        assert_contact(&square, &circle(12., 5., 3.), left, 1.);
+        assert_contact(&circle(5., 11., 2.), &square, up, 1.);
+        assert_contact(&circle(-2., 5., 3.), &triangle(Vec2::zero()), left, 1.);
+        assert_contact(&triangle(Vec2::new(0., 9.)), &square, up, 1.);
+        let square_polygon = Polygon::from_aabb(&Aabb::new(Vec2::zero(), Vec2::new(10., 10.)));
+        assert_contact(&square_polygon.into(), &aabb(8., 2., 18., 8.), left, 2.);
+    }
+
+    #[test]
+    fn circle_contact_at_a_corner_follows_the_diagonal() {
+        let contact = circle(12., 12., 3.)
+            .contact(&aabb(0., 0., 10., 10.))
+            .unwrap();
+        let diagonal = Vec2::new(1., 1.).normalize();
+        assert!((contact.normal - diagonal).len() < 1e-9);
+        assert!((contact.depth - (3. - 8f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn touching_shapes_do_not_overlap() {
+        let square = aabb(0., 0., 10., 10.);
+        let touching = [
+            aabb(10., 0., 20., 10.),
+            aabb(0., 10., 10., 20.),
+            aabb(10., 10., 20., 20.),
+            circle(15., 5., 5.),
+            circle(5., -3., 3.),
+            triangle(Vec2::new(10., 0.)),
+        ];
+        for other in &touching {
+            assert_eq!(square.contact(other), None, "{:?}", other);
+            assert_eq!(other.contact(&square), None, "{:?}", other);
+        }
+        assert_eq!(circle(0., 0., 5.).contact(&circle(10., 0., 5.)), None);
+        assert_eq!(
+            triangle(Vec2::zero()).contact(&circle(10., 10., 50f64.sqrt())),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_size_shapes() {
+        let square = aabb(0., 0., 10., 10.);
+        let point = aabb(5., 5., 5., 5.);
+        assert!(square.intersects(&point));
+        assert!(point.intersects(&square));
+        assert!(!point.intersects(&aabb(20., 20., 20., 20.)));
+        assert!(aabb(2., 2., 2., 2.).intersects(&triangle(Vec2::zero())));
+        assert!(!point.intersects(&triangle(Vec2::zero())));
+        assert!(point.intersects(&circle(5., 6., 2.)));
+
+        // the contact normal of concentric shapes is arbitrary, but must not be NaN
+        for (a, b) in &[
+            (circle(5., 5., 0.), circle(5., 5., 3.)),
+            (point.clone(), circle(5., 5., 3.)),
+            (circle(5., 5., 0.), square.clone()),
+        ] {
+            let contact = a.contact(b).expect("shapes should overlap");
+            assert!((contact.normal.len() - 1.).abs() < 1e-9, "{:?}", contact);
+            assert!(contact.depth > 0.);
+        }
+        assert_eq!(circle(20., 20., 0.).contact(&square), None);
+
+        let hit = Segment::new(Vec2::new(-10., 0.), Vec2::new(10., 0.))
+            .cast(&Circle::new(Vec2::zero(), 0.).into())
+            .unwrap();
+        assert_eq!(hit.time, 0.5);
+        assert_eq!(hit.normal, Vec2::new(-1., 0.));
+    }
+
+    #[test]
+    fn pairs_are_reported_once_for_items_spanning_several_cells() {
+        let mut hash = SpatialHash::new(10.);
+        hash.insert(1, Aabb::new(Vec2::new(-5., -5.), Vec2::new(25., 25.)));
+        hash.insert(2, Aabb::new(Vec2::new(-3., -3.), Vec2::new(15., 15.)));
+        hash.insert(3, Aabb::new(Vec2::new(40., 40.), Vec2::new(45., 45.)));
+        let pairs = hash.pairs();
+        assert_eq!(pairs.len(), 1, "{:?}", pairs);
+        assert!(pairs[0] == (1, 2) || pairs[0] == (2, 1));
+
+        let mut found = hash.query(&Aabb::new(Vec2::new(-10., -10.), Vec2::new(30., 30.)));
+        found.sort_unstable();
+        assert_eq!(found, vec![1, 2]);
+
+        hash.insert(2, Aabb::new(Vec2::new(30., 30.), Vec2::new(35., 35.)));
+        assert!(hash.pairs().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "bounds must be finite")]
+    fn spatial_hash_rejects_infinite_bounds() {
+        let mut hash = SpatialHash::new(10.);
+        hash.insert(1, Aabb::new(Vec2::zero(), Vec2::new(f64::INFINITY, 1.)));
+    }
+
+    #[test]
+    fn move_aabb_handles_negative_coordinates() {
+        let mut level = TileGrid::new(4, 4, 10.);
+        level.set_solid(1, 0, true);
+
+        // coming from the left of the grid, through the empty tiles outside of it
+        let player = Aabb::new(Vec2::new(-25., 0.), Vec2::new(-15., 10.));
+        let step = level.move_aabb(&player, Vec2::new(40., 0.));
+        assert!(step.blocked_x);
+        assert_eq!(step.offset, Vec2::new(25., 0.));
+
+        // aligned on a tile boundary left of the grid
+        let player = Aabb::new(Vec2::new(-20., 0.), Vec2::new(-10., 10.));
+        let step = level.move_aabb(&player, Vec2::new(25., 0.));
+        assert_eq!(step.offset, Vec2::new(20., 0.));
+
+        // coming from below the grid
+        let player = Aabb::new(Vec2::new(10., -30.), Vec2::new(20., -20.));
+        let step = level.move_aabb(&player, Vec2::new(0., 50.));
+        assert!(step.blocked_y && !step.blocked_x);
+        assert_eq!(step.offset, Vec2::new(0., 20.));
+
+        // leaving the grid is never blocked
+        let player = Aabb::new(Vec2::new(30., 0.), Vec2::new(40., 10.));
+        let step = level.move_aabb(&player, Vec2::new(-5., -30.));
+        assert!(!step.blocked_x && !step.blocked_y);
+        assert_eq!(step.offset, Vec2::new(-5., -30.));
+    }
+}
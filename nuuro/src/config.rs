@@ -0,0 +1,188 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! User preferences for the window, audio and controls.
+//!
+//! The config is read from a TOML file before the window is created, so it can override
+//! the `AppInfo` passed to `nuuro::run`. On desktop targets the file is `config.toml` in
+//! the user config directory (e.g. `~/.config/<storage name>/` on Linux, see
+//! `AppInfo::storage_name`), in the browser it is kept in `localStorage`.
+//! A missing or invalid config file is ignored.
+//!
+//! An options menu can change the config with `AppContext::set_config`, and write it
+//! back with `AppContext::save_config`:
+//!
+//! ```toml
+//! [window]
+//! width = 1280
+//! height = 720
+//! fullscreen = false
+//! vsync = true
+//!
+//! [audio]
+//! master_volume = 0.8
+//! music_volume = 0.5
+//! sound_volume = 1.0
+//!
+//! [controls]
+//! jump = ["key:Space", "button:South"]
+//! move_x = ["-key:Left", "key:Right", "axis:LeftStickX"]
+//! ```
+//!
+//! Unset values keep the defaults chosen by the app.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{InputMap, ParseBindingsError};
+use crate::app_info::AppInfo;
+use crate::core::CoreStorage;
+use crate::storage::StorageError;
+
+/// User preferences, see the module documentation.
+///
+/// # Example
+///
+/// ```rust
+/// use nuuro::Config;
+///
+/// let mut config = Config::from_toml("[audio]\nmusic_volume = 0.5").unwrap();
+/// assert_eq!(config.audio.music_volume, Some(0.5));
+/// assert_eq!(config.window.width, None);
+///
+/// config.window.vsync = Some(true);
+/// assert!(config.to_toml().unwrap().contains("vsync = true"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Window preferences.
+    pub window: WindowConfig,
+    /// Audio preferences.
+    pub audio: AudioConfig,
+    /// Bindings for each action of an `InputMap`, in the format used by
+    /// `InputMap::save_bindings`, e.g. `"key:Space"` or `"-axis:LeftStickY"`.
+    pub controls: BTreeMap<String, Vec<String>>,
+}
+
+/// Window preferences, overriding `AppInfo`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Initial native width of the window, see `AppInfo::native_dims`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Initial native height of the window, see `AppInfo::native_dims`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Whether the app runs in fullscreen mode, see `AppInfo::fullscreen`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    /// Whether vertical synchronization is enabled, see `AppInfo::vsync`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vsync: Option<bool>,
+}
+
+/// Audio preferences, applied to `AppContext::audio`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// See `Audio::set_master_volume`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub master_volume: Option<f32>,
+    /// See `Audio::set_music_volume`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub music_volume: Option<f32>,
+    /// See `Audio::set_sound_volume`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound_volume: Option<f32>,
+}
+
+impl Config {
+    /// Returns an empty config, which keeps every default.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Parses a config from TOML text.
+    pub fn from_toml(text: &str) -> Result<Config, StorageError> {
+        toml::from_str(text).map_err(|err| StorageError::Encoding(err.to_string()))
+    }
+
+    /// Formats the config as TOML text.
+    pub fn to_toml(&self) -> Result<String, StorageError> {
+        toml::to_string(self).map_err(|err| StorageError::Encoding(err.to_string()))
+    }
+
+    /// Loads the bindings in `controls` into `input`.
+    ///
+    /// Only the actions listed in `controls` are replaced, see `InputMap::load_bindings`.
+    pub fn apply_bindings(&self, input: &mut InputMap) -> Result<(), ParseBindingsError> {
+        let mut text = String::new();
+        for (action, bindings) in &self.controls {
+            for binding in bindings {
+                text.push_str(&format!("{} {}\n", action, binding));
+            }
+        }
+        input.load_bindings(&text)
+    }
+
+    /// Replaces `controls` with the current bindings of `input`.
+    pub fn set_bindings(&mut self, input: &InputMap) {
+        self.controls.clear();
+        for line in input.save_bindings().lines() {
+            if let Some((action, binding)) = line.split_once(' ') {
+                self.controls
+                    .entry(action.to_string())
+                    .or_default()
+                    .push(binding.to_string());
+            }
+        }
+    }
+
+    pub(crate) fn load(storage: &CoreStorage) -> Config {
+        let text = match storage.read_config() {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) | Err(StorageError::Unavailable) => return Config::default(),
+            Err(err) => {
+                crate::core::println(format!("Could not read config: {}", err));
+                return Config::default();
+            }
+        };
+        let config = String::from_utf8(text)
+            .map_err(|err| StorageError::Encoding(err.to_string()))
+            .and_then(|text| Config::from_toml(&text));
+        config.unwrap_or_else(|err| {
+            crate::core::println(format!("Ignoring invalid config: {}", err));
+            Config::default()
+        })
+    }
+
+    pub(crate) fn apply_to_info(&self, info: &mut AppInfo) {
+        let valid = |size: u32| (10..=3000).contains(&size);
+        if let Some(width) = self.window.width.filter(|&width| valid(width)) {
+            info.window_pixels.0 = width;
+        }
+        if let Some(height) = self.window.height.filter(|&height| valid(height)) {
+            info.window_pixels.1 = height;
+        }
+        if let Some(fullscreen) = self.window.fullscreen {
+            info.fullscreen = fullscreen;
+        }
+        if let Some(vsync) = self.window.vsync {
+            info.vsync = vsync;
+        }
+    }
+}
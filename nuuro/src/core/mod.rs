@@ -26,9 +26,40 @@ pub use self::wasm::*;
 
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::asset_id::AppAssetId;
+use crate::{App, AppContext};
+
 static APP_CREATED: AtomicBool = AtomicBool::new(false);
 
 fn mark_app_created_flag() {
     let previously_created = APP_CREATED.swap(true, Ordering::Relaxed);
     assert!(!previously_created, "Cannot construct more than one App.");
 }
+
+fn set_focused<AS: AppAssetId, AP: App<AS>>(app: &mut AP, ctx: &mut AppContext<AS>, focused: bool) {
+    if ctx.is_focused() != focused {
+        ctx.set_focused(focused);
+        if !focused {
+            // releases while unfocused are never reported, so keys would stay held forever
+            for key in ctx.release_all_keys() {
+                app.key_up(key, ctx);
+            }
+        }
+        app.focus_changed(focused, ctx);
+    }
+}
+
+fn set_suspended<AS: AppAssetId, AP: App<AS>>(
+    app: &mut AP,
+    ctx: &mut AppContext<AS>,
+    suspended: bool,
+) {
+    if ctx.is_suspended() != suspended {
+        ctx.set_suspended(suspended);
+        if suspended {
+            app.suspended(ctx);
+        } else {
+            app.resumed(ctx);
+        }
+    }
+}
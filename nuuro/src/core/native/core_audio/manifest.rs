@@ -0,0 +1,68 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+
+// Audio details of an asset, written by "nuuro_build" as one line per asset,
+// e.g. "ogg mp3 loop:44100:882000:44100"
+#[derive(Default)]
+pub struct ManifestEntry {
+    pub formats: Vec<String>,
+    pub loop_points: Option<LoopPoints>,
+}
+
+// Looped section of a track, in samples per channel
+#[derive(Copy, Clone)]
+pub struct LoopPoints {
+    pub start: u64,
+    pub end: u64,
+    pub sample_rate: u32,
+}
+
+impl ManifestEntry {
+    fn parse(line: &str) -> ManifestEntry {
+        let mut entry = ManifestEntry::default();
+        for token in line.split_whitespace() {
+            if let Some(points) = token.strip_prefix("loop:") {
+                entry.loop_points = parse_loop_points(points);
+            } else {
+                entry.formats.push(token.to_owned());
+            }
+        }
+        entry
+    }
+}
+
+fn parse_loop_points(points: &str) -> Option<LoopPoints> {
+    let mut values = points.split(':').map(|v| v.parse::<u64>().ok());
+    let start = values.next()??;
+    let end = values.next()??;
+    let sample_rate = values.next()?? as u32;
+    if start < end && sample_rate > 0 {
+        Some(LoopPoints {
+            start,
+            end,
+            sample_rate,
+        })
+    } else {
+        None
+    }
+}
+
+// Assets packed without a manifest are assumed to be `.ogg` files.
+pub fn read_manifest(prefix: &str) -> Vec<ManifestEntry> {
+    fs::read_to_string(format!("assets/{}.manifest", prefix))
+        .map(|manifest| manifest.lines().map(ManifestEntry::parse).collect())
+        .unwrap_or_else(|_| Vec::new())
+}
@@ -12,46 +12,207 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod manifest;
+mod output;
 mod sound_data;
 mod sound_source;
 
+use std::time::{Duration, Instant};
+
+use manifest::read_manifest;
+use output::Output;
 use sound_source::SoundSource;
 
+// Time between attempts to open an output device while there is none, doubling after
+// each failed attempt since opening a device is slow enough to cause a visible hitch
+const MIN_OUTPUT_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_OUTPUT_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// When no output device is available, or the device is lost, `output` is `None`
+// and audio is silently skipped until a device can be opened again. The music keeps
+// playing silently meanwhile, and restarts once a device is opened.
 pub struct CoreAudio {
+    output: Option<Output>,
+    last_open_attempt: Instant,
+    open_retry_interval: Duration,
     playing_music: Option<u16>,
+    music_paused: bool,
+    music_repeats: bool,
+    music_volume: f32,
     sounds: Vec<SoundSource>,
     musics: Vec<SoundSource>,
+    synth_sounds: Vec<SoundSource>,
 }
 
 impl CoreAudio {
     pub(crate) fn new(sound_count: u16, musics_count: u16) -> CoreAudio {
-        let device = rodio::default_output_device().unwrap();
-        let sounds: Vec<_> = (0..sound_count)
-            .map(|id| SoundSource::new(&device, &format!("assets/sound{}.ogg", id)).unwrap())
-            .collect();
-        let musics: Vec<_> = (0..musics_count)
-            .map(|id| SoundSource::new(&device, &format!("assets/music{}.ogg", id)).unwrap())
-            .collect();
-        CoreAudio {
-            sounds,
+        let output = Output::open();
+        if output.is_none() {
+            println!("No audio output device available, audio is disabled");
+        }
+        let mut audio = CoreAudio {
+            output,
+            last_open_attempt: Instant::now(),
+            open_retry_interval: MIN_OUTPUT_RETRY_INTERVAL,
             playing_music: None,
-            musics,
+            music_paused: false,
+            music_repeats: false,
+            music_volume: 1.,
+            sounds: load_sources("sound", sound_count),
+            musics: load_sources("music", musics_count),
+            synth_sounds: Vec::new(),
+        };
+        if let Some(ref output) = audio.output {
+            for source in audio.sounds.iter_mut().chain(audio.musics.iter_mut()) {
+                source.connect(output.device());
+            }
         }
+        audio
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.output.is_some()
+    }
+
+    // Switches to silent playback if the output device was lost, and back once
+    // a device can be opened again
+    pub fn check_output(&mut self) {
+        match self.output {
+            Some(ref mut output) => {
+                if !output.is_alive() {
+                    println!("Audio output device lost, audio is disabled");
+                    self.output = None;
+                    self.last_open_attempt = Instant::now();
+                    self.open_retry_interval = MIN_OUTPUT_RETRY_INTERVAL;
+                    for source in self.all_sources_mut() {
+                        source.disconnect();
+                    }
+                }
+            }
+            None => {
+                if self.last_open_attempt.elapsed() >= self.open_retry_interval {
+                    self.last_open_attempt = Instant::now();
+                    if !self.reopen_output() {
+                        self.open_retry_interval =
+                            (self.open_retry_interval * 2).min(MAX_OUTPUT_RETRY_INTERVAL);
+                    }
+                }
+            }
+        }
+    }
+
+    // Returns whether a device was opened
+    fn reopen_output(&mut self) -> bool {
+        let output = match Output::open() {
+            Some(output) => output,
+            None => return false,
+        };
+        println!("Audio output device available, audio is enabled");
+        for source in self.all_sources_mut() {
+            source.connect(output.device());
+        }
+        self.output = Some(output);
+        if let Some(music) = self.playing_music {
+            let music = &self.musics[music as usize];
+            music.play(self.music_volume, self.music_repeats);
+            music.set_paused(self.music_paused);
+        }
+        true
+    }
+
+    fn all_sources_mut(&mut self) -> impl Iterator<Item = &mut SoundSource> {
+        self.sounds
+            .iter_mut()
+            .chain(self.musics.iter_mut())
+            .chain(self.synth_sounds.iter_mut())
     }
 
     pub fn play_sound(&mut self, sound: u16, volume: f32) {
         self.sounds[sound as usize].play(volume, false);
     }
 
+    pub fn add_synth_sound(&mut self, samples: Vec<f32>, sample_rate: u32) -> u16 {
+        let mut source = SoundSource::from_samples(1, sample_rate, samples);
+        if let Some(ref output) = self.output {
+            source.connect(output.device());
+        }
+        self.synth_sounds.push(source);
+        (self.synth_sounds.len() - 1) as u16
+    }
+
+    pub fn play_synth_sound(&mut self, id: u16, volume: f32) {
+        self.synth_sounds[id as usize].play(volume, false);
+    }
+
     pub fn play_music(&mut self, music: u16, volume: f32, repeat: bool) {
+        self.stop_music();
         self.playing_music = Some(music);
+        self.music_repeats = repeat;
+        self.music_volume = volume;
         self.musics[music as usize].play(volume, repeat);
+        if self.music_paused {
+            self.musics[music as usize].set_paused(true);
+        }
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume;
+        if let Some(music) = self.playing_music {
+            self.musics[music as usize].set_volume(volume);
+        }
+    }
+
+    pub fn set_music_paused(&mut self, paused: bool) {
+        if paused != self.music_paused {
+            self.music_paused = paused;
+            for music in &self.musics {
+                music.set_paused(paused);
+            }
+        }
     }
 
     pub fn stop_music(&mut self) {
         if let Some(music) = self.playing_music {
-            self.musics[music as usize].stop();
+            if let Some(ref output) = self.output {
+                self.musics[music as usize].connect(output.device());
+            }
             self.playing_music = None;
         }
     }
+
+    // Returns true once when music played with `repeat` set to false reaches its end.
+    // Without an output device the music never ends, as it is not actually playing.
+    pub fn take_music_finished(&mut self) -> bool {
+        if self.output.is_none() {
+            return false;
+        }
+        match self.playing_music {
+            Some(music) if !self.music_repeats && self.musics[music as usize].is_finished() => {
+                self.playing_music = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn load_sources(prefix: &str, count: u16) -> Vec<SoundSource> {
+    let manifest = read_manifest(prefix);
+    (0..count)
+        .map(|id| {
+            let entry = manifest.get(id as usize);
+            // Every format listed in the manifest can be decoded natively,
+            // so the first one (the preferred source format) is used
+            let format = entry
+                .and_then(|entry| entry.formats.first())
+                .map(|format| format.as_str())
+                .unwrap_or("ogg");
+            let mut source =
+                SoundSource::new(&format!("assets/{}{}.{}", prefix, id, format)).unwrap();
+            if let Some(loop_points) = entry.and_then(|entry| entry.loop_points) {
+                source.set_loop_points(loop_points);
+            }
+            source
+        })
+        .collect()
 }
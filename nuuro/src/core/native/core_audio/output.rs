@@ -0,0 +1,76 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rodio::source::Zero;
+use rodio::{DeviceTrait, Source};
+
+// Time without audio being pulled from the device before it is considered lost
+const DEVICE_LOST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Audio output device.
+//
+// A silent "heartbeat" source is played continuously, so that while the device works
+// its stream keeps pulling samples from it. This is the only reliable way to detect
+// a lost device, since rodio keeps accepting sounds for a stream that is gone.
+pub struct Output {
+    device: rodio::Device,
+    _heartbeat_sink: rodio::Sink,
+    beats: Arc<AtomicUsize>,
+    last_beats: usize,
+    last_beat_time: Instant,
+}
+
+impl Output {
+    // Opens the default output device, or returns `None` if there is no usable device.
+    pub fn open() -> Option<Output> {
+        let device = rodio::default_output_device()?;
+        device.default_output_format().ok()?;
+
+        let beats = Arc::new(AtomicUsize::new(0));
+        let heartbeat_sink = rodio::Sink::new(&device);
+        let counter = beats.clone();
+        heartbeat_sink.append(Zero::<f32>::new(1, 44100).periodic_access(
+            Duration::from_millis(100),
+            move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            },
+        ));
+
+        Some(Output {
+            device,
+            _heartbeat_sink: heartbeat_sink,
+            beats,
+            last_beats: 0,
+            last_beat_time: Instant::now(),
+        })
+    }
+
+    pub fn device(&self) -> &rodio::Device {
+        &self.device
+    }
+
+    // Returns false if the device stopped pulling samples for a while
+    pub fn is_alive(&mut self) -> bool {
+        let beats = self.beats.load(Ordering::Relaxed);
+        if beats != self.last_beats {
+            self.last_beats = beats;
+            self.last_beat_time = Instant::now();
+        }
+        self.last_beat_time.elapsed() < DEVICE_LOST_TIMEOUT
+    }
+}
@@ -4,55 +4,120 @@ use rodio::buffer::SamplesBuffer;
 use rodio::Decoder;
 use rodio::Source as RSource;
 
+use super::manifest::LoopPoints;
 use super::sound_data::SoundData;
 
+// Decoded audio, played through a sink once connected to an output device.
 pub struct SoundSource {
-    sink: rodio::Sink,
+    sink: Option<rodio::Sink>,
     channels: u16,
     samples_rate: u32,
     samples: Vec<f32>,
+    // range of `samples` repeated after playing the start once, when looping
+    loop_range: Option<(usize, usize)>,
 }
 
 impl SoundSource {
     // Create a new `SoundSource` from the given file.
-    pub fn new(device: &rodio::Device, path: &str) -> io::Result<Self> {
+    pub fn new(path: &str) -> io::Result<Self> {
         let data = SoundData::new(path)?;
-        SoundSource::from_data(device, data)
+        SoundSource::from_data(data)
     }
 
     // Creates a new `SoundSource` using the given `SoundData` object.
-    pub fn from_data(device: &rodio::Device, data: SoundData) -> io::Result<Self> {
+    pub fn from_data(data: SoundData) -> io::Result<Self> {
         if !data.can_play() {
             panic!("Could not decode the given audio data");
         }
-        let sink = rodio::Sink::new(device);
         let cursor = io::Cursor::new(data);
         let src = Decoder::new(cursor).unwrap();
         Ok(SoundSource {
-            sink,
+            sink: None,
             channels: src.channels(),
             samples_rate: src.sample_rate(),
             samples: src.convert_samples().collect::<Vec<f32>>(),
+            loop_range: None,
         })
     }
 
+    // Creates a new `SoundSource` from raw samples, interleaved by channel.
+    pub fn from_samples(channels: u16, samples_rate: u32, samples: Vec<f32>) -> Self {
+        SoundSource {
+            sink: None,
+            channels,
+            samples_rate,
+            samples,
+            loop_range: None,
+        }
+    }
+
+    // Sets the section repeated by `play`, converting the points to the decoded sample rate.
+    pub fn set_loop_points(&mut self, points: LoopPoints) {
+        let to_index = |sample: u64| {
+            let frame = sample * self.samples_rate as u64 / points.sample_rate as u64;
+            (frame as usize * self.channels as usize).min(self.samples.len())
+        };
+        let (start, end) = (to_index(points.start), to_index(points.end));
+        self.loop_range = if start < end {
+            Some((start, end))
+        } else {
+            None
+        };
+    }
+
+    // Creates a new sink on the given device, discarding anything queued on the previous one
+    pub fn connect(&mut self, device: &rodio::Device) {
+        self.sink = Some(rodio::Sink::new(device));
+    }
+
+    pub fn disconnect(&mut self) {
+        self.sink = None;
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if let Some(ref sink) = self.sink {
+            if paused {
+                sink.pause();
+            } else {
+                sink.play();
+            }
+        }
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        if let Some(ref sink) = self.sink {
+            sink.set_volume(volume);
+        }
+    }
+
+    // Sources without an output device finish immediately
+    pub fn is_finished(&self) -> bool {
+        self.sink.as_ref().map(|sink| sink.empty()).unwrap_or(true)
+    }
+
     fn to_buffer(&self) -> SamplesBuffer<f32> {
         SamplesBuffer::new(self.channels, self.samples_rate, self.samples.clone())
     }
 
     pub fn play(&self, volume: f32, repeat: bool) {
-        self.sink.set_volume(volume);
-        if repeat {
+        let sink = match self.sink {
+            Some(ref sink) => sink,
+            None => return,
+        };
+        sink.set_volume(volume);
+        if let (true, Some((start, end))) = (repeat, self.loop_range) {
+            let intro = self.samples[..end].to_vec();
+            let section = self.samples[start..end].to_vec();
+            sink.append(SamplesBuffer::new(self.channels, self.samples_rate, intro));
+            sink.append(
+                SamplesBuffer::new(self.channels, self.samples_rate, section).repeat_infinite(),
+            );
+        } else if repeat {
             let sound = self.to_buffer().repeat_infinite();
-            self.sink.append(sound);
+            sink.append(sound);
         } else {
             let sound = self.to_buffer();
-            self.sink.append(sound);
+            sink.append(sound);
         }
     }
-
-    pub fn stop(&mut self) {
-        let device = rodio::default_output_device().unwrap();
-        self.sink = rodio::Sink::new(&device);
-    }
 }
@@ -0,0 +1,143 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::storage::StorageError;
+
+const SLOT_EXTENSION: &str = "sav";
+const CONFIG_FILE: &str = "config.toml";
+
+pub struct CoreStorage {
+    dir: Option<PathBuf>,
+    config_path: Option<PathBuf>,
+}
+
+impl CoreStorage {
+    pub fn new(name: &str) -> CoreStorage {
+        CoreStorage {
+            dir: data_dir().map(|dir| dir.join(name)),
+            config_path: config_dir().map(|dir| dir.join(name).join(CONFIG_FILE)),
+        }
+    }
+
+    fn dir(&self) -> Result<&PathBuf, StorageError> {
+        self.dir.as_ref().ok_or(StorageError::Unavailable)
+    }
+
+    fn slot_path(&self, slot: &str) -> Result<PathBuf, StorageError> {
+        Ok(self.dir()?.join(slot).with_extension(SLOT_EXTENSION))
+    }
+
+    pub fn read(&self, slot: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        read_file(&self.slot_path(slot)?)
+    }
+
+    pub fn write(&mut self, slot: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        write_file(&self.slot_path(slot)?, bytes)
+    }
+
+    pub fn read_config(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        read_file(self.config_path.as_ref().ok_or(StorageError::Unavailable)?)
+    }
+
+    pub fn write_config(&mut self, bytes: &[u8]) -> Result<(), StorageError> {
+        write_file(
+            self.config_path.as_ref().ok_or(StorageError::Unavailable)?,
+            bytes,
+        )
+    }
+
+    pub fn remove(&mut self, slot: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.slot_path(slot)?) {
+            Ok(()) => Ok(()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn slots(&self) -> Result<Vec<String>, StorageError> {
+        let entries = match fs::read_dir(self.dir()?) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut slots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == SLOT_EXTENSION) {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    slots.push(stem.to_string());
+                }
+            }
+        }
+        Ok(slots)
+    }
+}
+
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>, StorageError> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), StorageError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    // the data is synced to disk before replacing the old file, so a crash
+    // leaves either the old or the new contents
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Base directory for per-user application data, following the platform conventions
+fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+// Base directory for per-user configuration files, following the platform conventions
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) || cfg!(target_os = "macos") {
+        data_dir()
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    }
+}
+
+// Reads an absolute directory path from an environment variable, ignoring relative paths
+// as required by the XDG base directory specification
+fn env_dir(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
@@ -12,27 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
-
 use glutin::dpi::LogicalPosition;
-use glutin::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+use glutin::{
+    DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, Touch,
+    TouchPhase, VirtualKeyCode, WindowEvent,
+};
 
 use crate::asset_id::AppAssetId;
-use crate::input::KeyCode;
+use crate::core;
+use crate::input::{is_text_char, KeyCode, TouchPoint};
 use crate::renderer::Renderer;
 use crate::{App, AppContext};
 
+// Roughly how many pixels a single mouse wheel notch scrolls in most applications,
+// used to bring trackpad deltas to the same scale as mouse wheel deltas.
+const PIXELS_PER_WHEEL_NOTCH: f64 = 100.;
+
 pub struct EventHandler {
-    held_keys: HashSet<KeyCode>,
+    touches: Vec<TouchPoint>,
+    suspended: bool,
 }
 
 impl EventHandler {
     pub fn new() -> EventHandler {
         EventHandler {
-            held_keys: HashSet::new(),
+            touches: Vec::new(),
+            suspended: false,
         }
     }
 
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
     pub fn process_events<AS: AppAssetId, AP: App<AS>>(
         &mut self,
         event: Event,
@@ -40,7 +52,18 @@ impl EventHandler {
         ctx: &mut AppContext<AS>,
         renderer: &Renderer<AS>,
     ) -> bool {
-        if let Event::WindowEvent { event, .. } = event {
+        if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+            ..
+        } = event
+        {
+            // device events are reported even when another window has focus
+            if ctx.is_focused() {
+                ctx.add_mouse_motion(dx, dy);
+            }
+        } else if let Event::Suspended(suspended) = event {
+            self.suspended = suspended;
+        } else if let Event::WindowEvent { event, .. } = event {
             match event {
                 WindowEvent::KeyboardInput {
                     input:
@@ -54,7 +77,7 @@ impl EventHandler {
                     ElementState::Pressed => {
                         if let Some(keycode) = virtual_keycode {
                             if let Some(keycode) = glutin_to_nuuro_key(keycode) {
-                                if self.held_keys.insert(keycode) {
+                                if ctx.press_key(keycode) {
                                     app.key_down(keycode, ctx);
                                 }
                             }
@@ -63,33 +86,81 @@ impl EventHandler {
                     ElementState::Released => {
                         if let Some(keycode) = virtual_keycode {
                             if let Some(keycode) = glutin_to_nuuro_key(keycode) {
-                                if self.held_keys.remove(&keycode) {
+                                if ctx.release_key(keycode) {
                                     app.key_up(keycode, ctx);
                                 }
                             }
                         }
                     }
                 },
+                // the cursor is kept at the window center while the pointer is locked
                 WindowEvent::CursorMoved {
                     position: LogicalPosition { x, y },
                     ..
-                } => ctx.set_cursor(renderer.to_app_pos(x as i32, y as i32)),
+                } if !ctx.is_pointer_locked() => {
+                    ctx.set_cursor(renderer.to_app_pos(x as i32, y as i32))
+                }
                 WindowEvent::MouseInput { state, button, .. } => match state {
                     ElementState::Pressed => {
                         if let Some(button) = mouse_button_to_nuuro_key(button) {
-                            if self.held_keys.insert(button) {
+                            if ctx.press_key(button) {
                                 app.key_down(button, ctx);
                             }
                         }
                     }
                     ElementState::Released => {
                         if let Some(button) = mouse_button_to_nuuro_key(button) {
-                            if self.held_keys.remove(&button) {
+                            if ctx.release_key(button) {
                                 app.key_up(button, ctx);
                             }
                         }
                     }
                 },
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (dx, dy) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
+                        MouseScrollDelta::PixelDelta(LogicalPosition { x, y }) => {
+                            (x / PIXELS_PER_WHEEL_NOTCH, y / PIXELS_PER_WHEEL_NOTCH)
+                        }
+                    };
+                    app.mouse_wheel(dx, dy, ctx);
+                }
+                WindowEvent::Touch(Touch {
+                    phase,
+                    location: LogicalPosition { x, y },
+                    id,
+                    ..
+                }) => {
+                    let id = id as u32;
+                    match phase {
+                        TouchPhase::Started => self.touches.push(TouchPoint { id, x, y }),
+                        TouchPhase::Moved => {
+                            if let Some(touch) = self.touches.iter_mut().find(|t| t.id == id) {
+                                touch.x = x;
+                                touch.y = y;
+                            }
+                        }
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            self.touches.retain(|t| t.id != id)
+                        }
+                    }
+                    ctx.set_touches_pos(self.touches.clone(), |x, y| renderer.to_app_pos_f64(x, y));
+                    // same as in the browser, every touch starting or ending is reported
+                    // through `KeyCode::Touch`, with the details available in `ctx.touches()`
+                    match phase {
+                        TouchPhase::Started => app.key_down(KeyCode::Touch, ctx),
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            app.key_up(KeyCode::Touch, ctx)
+                        }
+                        TouchPhase::Moved => {}
+                    }
+                }
+                WindowEvent::Focused(focused) => core::set_focused(app, ctx, focused),
+                WindowEvent::ReceivedCharacter(c)
+                    if ctx.is_text_input_active() && is_text_char(c) =>
+                {
+                    app.text_input(c.encode_utf8(&mut [0; 4]), ctx)
+                }
                 WindowEvent::CloseRequested => {
                     return false;
                 }
@@ -147,6 +218,60 @@ fn glutin_to_nuuro_key(key: VirtualKeyCode) -> Option<KeyCode> {
         VirtualKeyCode::Space => Some(KeyCode::Space),
         VirtualKeyCode::Back => Some(KeyCode::Backspace),
         VirtualKeyCode::Delete => Some(KeyCode::Delete),
+        VirtualKeyCode::Tab => Some(KeyCode::Tab),
+        VirtualKeyCode::Insert => Some(KeyCode::Insert),
+        VirtualKeyCode::Home => Some(KeyCode::Home),
+        VirtualKeyCode::End => Some(KeyCode::End),
+        VirtualKeyCode::PageUp => Some(KeyCode::PageUp),
+        VirtualKeyCode::PageDown => Some(KeyCode::PageDown),
+        VirtualKeyCode::Capital => Some(KeyCode::CapsLock),
+        VirtualKeyCode::F1 => Some(KeyCode::F1),
+        VirtualKeyCode::F2 => Some(KeyCode::F2),
+        VirtualKeyCode::F3 => Some(KeyCode::F3),
+        VirtualKeyCode::F4 => Some(KeyCode::F4),
+        VirtualKeyCode::F5 => Some(KeyCode::F5),
+        VirtualKeyCode::F6 => Some(KeyCode::F6),
+        VirtualKeyCode::F7 => Some(KeyCode::F7),
+        VirtualKeyCode::F8 => Some(KeyCode::F8),
+        VirtualKeyCode::F9 => Some(KeyCode::F9),
+        VirtualKeyCode::F10 => Some(KeyCode::F10),
+        VirtualKeyCode::F11 => Some(KeyCode::F11),
+        VirtualKeyCode::F12 => Some(KeyCode::F12),
+        VirtualKeyCode::LShift => Some(KeyCode::LShift),
+        VirtualKeyCode::RShift => Some(KeyCode::RShift),
+        VirtualKeyCode::LControl => Some(KeyCode::LControl),
+        VirtualKeyCode::RControl => Some(KeyCode::RControl),
+        VirtualKeyCode::LAlt => Some(KeyCode::LAlt),
+        VirtualKeyCode::RAlt => Some(KeyCode::RAlt),
+        VirtualKeyCode::LWin => Some(KeyCode::LMeta),
+        VirtualKeyCode::RWin => Some(KeyCode::RMeta),
+        VirtualKeyCode::Numpad0 => Some(KeyCode::Numpad0),
+        VirtualKeyCode::Numpad1 => Some(KeyCode::Numpad1),
+        VirtualKeyCode::Numpad2 => Some(KeyCode::Numpad2),
+        VirtualKeyCode::Numpad3 => Some(KeyCode::Numpad3),
+        VirtualKeyCode::Numpad4 => Some(KeyCode::Numpad4),
+        VirtualKeyCode::Numpad5 => Some(KeyCode::Numpad5),
+        VirtualKeyCode::Numpad6 => Some(KeyCode::Numpad6),
+        VirtualKeyCode::Numpad7 => Some(KeyCode::Numpad7),
+        VirtualKeyCode::Numpad8 => Some(KeyCode::Numpad8),
+        VirtualKeyCode::Numpad9 => Some(KeyCode::Numpad9),
+        VirtualKeyCode::Add => Some(KeyCode::NumpadAdd),
+        VirtualKeyCode::Subtract => Some(KeyCode::NumpadSubtract),
+        VirtualKeyCode::Multiply => Some(KeyCode::NumpadMultiply),
+        VirtualKeyCode::Divide => Some(KeyCode::NumpadDivide),
+        VirtualKeyCode::Decimal => Some(KeyCode::NumpadDecimal),
+        VirtualKeyCode::NumpadEnter => Some(KeyCode::NumpadEnter),
+        VirtualKeyCode::Minus => Some(KeyCode::Minus),
+        VirtualKeyCode::Equals => Some(KeyCode::Equals),
+        VirtualKeyCode::LBracket => Some(KeyCode::LBracket),
+        VirtualKeyCode::RBracket => Some(KeyCode::RBracket),
+        VirtualKeyCode::Backslash => Some(KeyCode::Backslash),
+        VirtualKeyCode::Semicolon => Some(KeyCode::Semicolon),
+        VirtualKeyCode::Apostrophe => Some(KeyCode::Apostrophe),
+        VirtualKeyCode::Comma => Some(KeyCode::Comma),
+        VirtualKeyCode::Period => Some(KeyCode::Period),
+        VirtualKeyCode::Slash => Some(KeyCode::Slash),
+        VirtualKeyCode::Grave => Some(KeyCode::Grave),
         _ => None,
     }
 }
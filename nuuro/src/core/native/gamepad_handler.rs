@@ -0,0 +1,126 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use gilrs::{Axis, Button, EventType, Gilrs, GilrsBuilder};
+
+use crate::asset_id::AppAssetId;
+use crate::gamepad::{self, GamepadAxis, GamepadButton, GamepadEvent, GamepadId};
+use crate::{App, AppContext};
+
+pub struct GamepadHandler {
+    gilrs: Option<Gilrs>,
+    initial: Vec<GamepadId>,
+}
+
+impl GamepadHandler {
+    pub fn new() -> GamepadHandler {
+        // filters are disabled because deadzones are applied by nuuro itself
+        let gilrs = match GilrsBuilder::new().with_default_filters(false).build() {
+            Ok(gilrs) => Some(gilrs),
+            Err(gilrs::Error::NotImplemented(gilrs)) => Some(gilrs),
+            Err(err) => {
+                println!("Gamepad support unavailable: {}", err);
+                None
+            }
+        };
+        let initial = gilrs
+            .iter()
+            .flat_map(|gilrs| gilrs.gamepads())
+            .map(|(id, _)| GamepadId(id.into()))
+            .collect();
+        GamepadHandler { gilrs, initial }
+    }
+
+    pub fn process_events<AS: AppAssetId, AP: App<AS>>(
+        &mut self,
+        app: &mut AP,
+        ctx: &mut AppContext<AS>,
+    ) {
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        for id in self.initial.drain(..) {
+            gamepad::handle_event(GamepadEvent::Connected(id), app, ctx);
+        }
+        while let Some(event) = gilrs.next_event() {
+            let id = GamepadId(event.id.into());
+            match event.event {
+                EventType::Connected => {
+                    gamepad::handle_event(GamepadEvent::Connected(id), app, ctx);
+                }
+                EventType::Disconnected => {
+                    gamepad::handle_event(GamepadEvent::Disconnected(id), app, ctx);
+                }
+                EventType::ButtonChanged(button, value, _) => {
+                    if let Some(button) = gilrs_to_nuuro_button(button) {
+                        gamepad::handle_event(GamepadEvent::Button(id, button, value), app, ctx);
+                    }
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    for event in gilrs_axis_events(id, axis, value) {
+                        gamepad::handle_event(event, app, ctx);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn gilrs_to_nuuro_button(button: Button) -> Option<GamepadButton> {
+    match button {
+        Button::South => Some(GamepadButton::South),
+        Button::East => Some(GamepadButton::East),
+        Button::West => Some(GamepadButton::West),
+        Button::North => Some(GamepadButton::North),
+        Button::LeftTrigger => Some(GamepadButton::LeftBumper),
+        Button::RightTrigger => Some(GamepadButton::RightBumper),
+        Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger),
+        Button::RightTrigger2 => Some(GamepadButton::RightTrigger),
+        Button::Select => Some(GamepadButton::Select),
+        Button::Start => Some(GamepadButton::Start),
+        Button::LeftThumb => Some(GamepadButton::LeftStick),
+        Button::RightThumb => Some(GamepadButton::RightStick),
+        Button::DPadUp => Some(GamepadButton::DPadUp),
+        Button::DPadDown => Some(GamepadButton::DPadDown),
+        Button::DPadLeft => Some(GamepadButton::DPadLeft),
+        Button::DPadRight => Some(GamepadButton::DPadRight),
+        Button::Mode => Some(GamepadButton::Mode),
+        _ => None,
+    }
+}
+
+fn gilrs_axis_events(id: GamepadId, axis: Axis, value: f32) -> Vec<GamepadEvent> {
+    // some gamepads report the D-pad as a pair of axes instead of four buttons,
+    // and the analog triggers as axes going from -1 (released) to 1 (fully pressed)
+    let trigger = |button| vec![GamepadEvent::Button(id, button, (value + 1.) / 2.)];
+    let dpad = |negative, positive| {
+        vec![
+            GamepadEvent::Button(id, negative, if value < -0.5 { 1. } else { 0. }),
+            GamepadEvent::Button(id, positive, if value > 0.5 { 1. } else { 0. }),
+        ]
+    };
+    match axis {
+        Axis::LeftStickX => vec![GamepadEvent::Axis(id, GamepadAxis::LeftStickX, value)],
+        Axis::LeftStickY => vec![GamepadEvent::Axis(id, GamepadAxis::LeftStickY, value)],
+        Axis::RightStickX => vec![GamepadEvent::Axis(id, GamepadAxis::RightStickX, value)],
+        Axis::RightStickY => vec![GamepadEvent::Axis(id, GamepadAxis::RightStickY, value)],
+        Axis::LeftZ => trigger(GamepadButton::LeftTrigger),
+        Axis::RightZ => trigger(GamepadButton::RightTrigger),
+        Axis::DPadX => dpad(GamepadButton::DPadLeft, GamepadButton::DPadRight),
+        Axis::DPadY => dpad(GamepadButton::DPadDown, GamepadButton::DPadUp),
+        _ => Vec::new(),
+    }
+}
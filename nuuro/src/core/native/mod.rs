@@ -14,16 +14,19 @@
 
 mod app_clock;
 mod core_audio;
+mod core_storage;
 mod event_handler;
+mod gamepad_handler;
 
 pub use self::core_audio::CoreAudio;
+pub use self::core_storage::CoreStorage;
 
 use std::ffi::CStr;
 use std::fs::File;
 use std::io::BufReader;
 use std::time::Instant;
 
-use glutin::dpi::LogicalSize;
+use glutin::dpi::{LogicalPosition, LogicalSize};
 use glutin::EventsLoop;
 use glutin::WindowBuilder;
 use glutin::{ContextBuilder, ContextTrait, WindowedContext};
@@ -32,15 +35,17 @@ use gl::types::*;
 
 use self::app_clock::AppClock;
 use self::event_handler::EventHandler;
-use super::mark_app_created_flag;
+use self::gamepad_handler::GamepadHandler;
+use super::{mark_app_created_flag, set_suspended};
 use crate::app_info::AppInfo;
 use crate::asset_id::{AppAssetId, IdU16};
+use crate::config::Config;
 use crate::renderer::atlas::Atlas;
 use crate::renderer::core_renderer::CoreRenderer;
 use crate::renderer::core_renderer::Texture;
 use crate::renderer::render_buffer::RenderBuffer;
 use crate::renderer::Renderer;
-use crate::{timer, App, AppContext};
+use crate::{App, AppContext};
 
 /// Macro to be placed in the `main.rs` file for a Nuuro app.
 ///
@@ -51,14 +56,19 @@ macro_rules! nuuro_header {
     () => {};
 }
 
-pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut app: AP) {
+pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(mut info: AppInfo, mut app: AP) {
     mark_app_created_flag();
 
+    let core_storage = CoreStorage::new(&info.effective_storage_name());
+    let config = Config::load(&core_storage);
+    config.apply_to_info(&mut info);
+
     let core_audio = CoreAudio::new(AS::Sound::count(), AS::Music::count());
 
     let mut events_loop = EventsLoop::new();
 
     let mut event_handler = EventHandler::new();
+    let mut gamepad_handler = GamepadHandler::new();
     let window = WindowBuilder::new()
         .with_title(info.title)
         .with_dimensions(LogicalSize::new(
@@ -68,6 +78,7 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
         .with_resizable(info.resizable);
     let gl_context = ContextBuilder::new()
         .with_gl_debug_flag(true)
+        .with_vsync(info.vsync)
         .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 0)))
         .build_windowed(window, &events_loop)
         .unwrap();
@@ -81,7 +92,18 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
 
     gl_error_check();
 
-    let mut ctx = AppContext::new(core_audio, renderer.app_dims(), renderer.native_px());
+    let mut ctx = AppContext::new(
+        core_audio,
+        core_storage,
+        info.save_schema_version,
+        renderer.app_dims(),
+        renderer.native_px(),
+    );
+    ctx.set_pause_when_inactive(info.pause_when_inactive);
+    ctx.set_config(config);
+    if info.fullscreen {
+        ctx.request_fullscreen();
+    }
 
     if info.print_gl_info {
         print_gl_info();
@@ -90,6 +112,7 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
     app.start(&mut ctx);
 
     let mut clock = AppClock::new(timer, &info);
+    let mut cursor_hidden = false;
 
     loop {
         events_loop.poll_events(|event| {
@@ -97,6 +120,7 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
                 ctx.close();
             }
         });
+        gamepad_handler.process_events(&mut app, &mut ctx);
 
         unsafe {
             gl::ClearColor(0., 0., 0., 1.0);
@@ -108,10 +132,21 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
             gl_context.window().get_inner_size().unwrap().height as u32,
         );
 
-        if screen_dims.0 > 0 && screen_dims.1 > 0 {
+        // a minimized window has no area on most platforms
+        let minimized = screen_dims.0 == 0 || screen_dims.1 == 0;
+        set_suspended(
+            &mut app,
+            &mut ctx,
+            minimized || event_handler.is_suspended(),
+        );
+
+        if !minimized {
             renderer.set_screen_dims(screen_dims);
             ctx.set_dims(renderer.app_dims(), renderer.native_px());
             app.render(&mut renderer, &ctx);
+            if let Some(sprite_id) = ctx.cursor_sprite_id() {
+                renderer.draw_cursor(ctx.cursor(), sprite_id);
+            }
             renderer.flush();
         }
 
@@ -134,9 +169,43 @@ pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, mut a
             (false, false) | (true, true) => {}
         }
 
-        let normalized_elapsed = elapsed.min(crate::MAX_TIMESTEP);
-        timer::update_all(normalized_elapsed);
-        app.advance(normalized_elapsed, &mut ctx);
+        let hide_cursor = !ctx.shows_os_cursor();
+        if hide_cursor != cursor_hidden {
+            gl_context.window().hide_cursor(hide_cursor);
+            cursor_hidden = hide_cursor;
+        }
+
+        match (ctx.is_pointer_locked(), ctx.desires_pointer_lock()) {
+            (false, true) => {
+                let locked = gl_context.window().grab_cursor(true).is_ok();
+                ctx.set_is_pointer_locked(locked);
+            }
+            (true, false) => {
+                let _ = gl_context.window().grab_cursor(false);
+                ctx.set_is_pointer_locked(false);
+            }
+            (false, false) | (true, true) => {}
+        }
+        if ctx.is_pointer_locked() {
+            // keep the grabbed cursor away from the window edges,
+            // so that relative motion keeps being reported
+            if let Some(size) = gl_context.window().get_inner_size() {
+                let center = LogicalPosition::new(size.width / 2., size.height / 2.);
+                let _ = gl_context.window().set_cursor_position(center);
+            }
+        }
+
+        ctx.audio.check_output();
+        if ctx.audio.take_music_finished() {
+            app.music_finished(&mut ctx);
+        }
+
+        if !ctx.is_auto_paused() {
+            let normalized_elapsed = elapsed.min(crate::MAX_TIMESTEP);
+            let seconds = ctx.advance_clocks(normalized_elapsed);
+            app.advance(seconds, &mut ctx);
+        }
+        ctx.end_frame();
         if ctx.take_close_request() {
             break;
         }
@@ -16,23 +16,23 @@ pub mod wasm_exports;
 pub mod wasm_imports;
 
 use std::cell::{self, RefCell};
-use std::collections::HashSet;
 use std::ffi::CString;
 use std::io::Cursor;
 use std::mem;
 use std::os::raw::{c_int, c_void};
 
 use self::wasm_imports::*;
-use super::mark_app_created_flag;
+use super::{mark_app_created_flag, set_focused, set_suspended};
 use crate::app_info::AppInfo;
 use crate::asset_id::{AppAssetId, IdU16};
-use crate::input::KeyCode;
-use crate::input::TouchPoint;
+use crate::config::Config;
+use crate::gamepad::{self, GamepadEvent};
+use crate::input::{is_text_char, KeyCode, TouchPoint};
 use crate::renderer::atlas::Atlas;
 use crate::renderer::core_renderer::CoreRenderer;
 use crate::renderer::render_buffer::RenderBuffer;
 use crate::renderer::Renderer;
-use crate::timer;
+use crate::storage::StorageError;
 use crate::{App, AppContext};
 
 pub struct CoreAudio;
@@ -43,6 +43,20 @@ impl CoreAudio {
             nuuroWasmPlaySound(id as c_int, volume);
         }
     }
+    pub fn add_synth_sound(&mut self, samples: Vec<f32>, sample_rate: u32) -> u16 {
+        unsafe {
+            nuuroWasmAddSynthSound(
+                samples.len(),
+                samples.as_ptr() as *const c_void,
+                sample_rate as c_int,
+            ) as u16
+        }
+    }
+    pub fn play_synth_sound(&mut self, id: u16, volume: f32) {
+        unsafe {
+            nuuroWasmPlaySynthSound(id as c_int, volume);
+        }
+    }
     pub fn play_music(&mut self, id: u16, volume: f32, loops: bool) {
         unsafe {
             if loops {
@@ -57,6 +71,110 @@ impl CoreAudio {
             nuuroWasmStopMusic();
         }
     }
+    pub fn is_available(&self) -> bool {
+        unsafe { nuuroWasmIsAudioAvailable() != 0 }
+    }
+    pub fn set_music_volume(&mut self, volume: f32) {
+        unsafe {
+            nuuroWasmSetMusicVolume(volume);
+        }
+    }
+    pub fn set_music_paused(&mut self, paused: bool) {
+        unsafe {
+            nuuroWasmSetMusicPaused(if paused { 1 } else { 0 });
+        }
+    }
+}
+
+// Slots are kept base64-encoded in localStorage under "<storage name>/<slot>",
+// and the config under "<storage name>.config"
+pub struct CoreStorage {
+    prefix: String,
+    config_key: String,
+}
+
+impl CoreStorage {
+    pub fn new(name: &str) -> CoreStorage {
+        CoreStorage {
+            prefix: format!("{}/", name),
+            config_key: format!("{}.config", name),
+        }
+    }
+
+    fn key(&self, slot: &str) -> String {
+        format!("{}{}", self.prefix, slot)
+    }
+
+    // Fetches the result of a previous storage query that returned `size`
+    fn take_result(size: c_int) -> Result<Option<Vec<u8>>, StorageError> {
+        match size {
+            -2 => Err(StorageError::Unavailable),
+            -1 => Ok(None),
+            size => {
+                let mut bytes = vec![0u8; size as usize];
+                unsafe {
+                    nuuroWasmStorageResultFill(bytes.as_mut_ptr() as *mut c_void);
+                }
+                Ok(Some(bytes))
+            }
+        }
+    }
+
+    pub fn read(&self, slot: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        CoreStorage::read_key(&self.key(slot))
+    }
+
+    pub fn write(&mut self, slot: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        CoreStorage::write_key(&self.key(slot), bytes)
+    }
+
+    pub fn read_config(&self) -> Result<Option<Vec<u8>>, StorageError> {
+        CoreStorage::read_key(&self.config_key)
+    }
+
+    pub fn write_config(&mut self, bytes: &[u8]) -> Result<(), StorageError> {
+        CoreStorage::write_key(&self.config_key, bytes)
+    }
+
+    fn read_key(key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let size = unsafe { nuuroWasmStorageRead(key.len(), key.as_ptr() as *const c_void) };
+        CoreStorage::take_result(size)
+    }
+
+    fn write_key(key: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        let written = unsafe {
+            nuuroWasmStorageWrite(
+                key.len(),
+                key.as_ptr() as *const c_void,
+                bytes.len(),
+                bytes.as_ptr() as *const c_void,
+            )
+        };
+        if written != 0 {
+            Ok(())
+        } else {
+            Err(StorageError::Unavailable)
+        }
+    }
+
+    pub fn remove(&mut self, slot: &str) -> Result<(), StorageError> {
+        let key = self.key(slot);
+        let removed = unsafe { nuuroWasmStorageRemove(key.len(), key.as_ptr() as *const c_void) };
+        if removed != 0 {
+            Ok(())
+        } else {
+            Err(StorageError::Unavailable)
+        }
+    }
+
+    pub fn slots(&self) -> Result<Vec<String>, StorageError> {
+        let size = unsafe {
+            nuuroWasmStorageList(self.prefix.len(), self.prefix.as_ptr() as *const c_void)
+        };
+        let bytes = CoreStorage::take_result(size)?.unwrap_or_default();
+        let list = String::from_utf8(bytes).map_err(|_| StorageError::Corrupt)?;
+        Ok(list.lines().map(|slot| slot.to_string()).collect())
+    }
 }
 
 trait TraitAppRunner {
@@ -66,6 +184,13 @@ trait TraitAppRunner {
     fn update_cursor(&mut self, cursor_x: i32, cursor_y: i32);
     fn update_touches(&mut self, touches: Vec<TouchPoint>);
     fn input(&mut self, key: KeyCode, down: bool) -> bool;
+    fn music_finished(&mut self) -> bool;
+    fn gamepad_event(&mut self, event: GamepadEvent) -> bool;
+    fn mouse_wheel(&mut self, dx: f64, dy: f64) -> bool;
+    fn mouse_motion(&mut self, dx: f64, dy: f64);
+    fn focus_changed(&mut self, focused: bool) -> bool;
+    fn visibility_changed(&mut self, visible: bool) -> bool;
+    fn text_input(&mut self, text: &str) -> bool;
     fn music_count(&self) -> u16;
     fn sound_count(&self) -> u16;
     fn on_restart(&mut self);
@@ -103,7 +228,8 @@ struct AppRunner<AS: AppAssetId, AP: App<AS>> {
     renderer: Option<Renderer<AS>>,
     ctx: AppContext<AS>,
     last_time_sec: Option<f64>,
-    held_keys: HashSet<KeyCode>,
+    text_input_active: bool,
+    cursor_visible: bool,
 }
 
 impl<AS: AppAssetId, AP: App<AS>> AppRunner<AS, AP> {
@@ -120,6 +246,38 @@ impl<AS: AppAssetId, AP: App<AS>> AppRunner<AS, AP> {
         }
     }
 
+    fn update_is_pointer_locked(&mut self) {
+        self.ctx
+            .set_is_pointer_locked(unsafe { nuuroWasmIsPointerLocked() != 0 });
+    }
+
+    fn resolve_pointer_lock_requests(&self) {
+        match (
+            self.ctx.is_pointer_locked(),
+            self.ctx.desires_pointer_lock(),
+        ) {
+            (false, true) => unsafe { nuuroWasmRequestPointerLock() },
+            (true, false) => unsafe { nuuroWasmExitPointerLock() },
+            (false, false) | (true, true) => {}
+        }
+    }
+
+    fn resolve_cursor_visibility(&mut self) {
+        let visible = self.ctx.shows_os_cursor();
+        if visible != self.cursor_visible {
+            self.cursor_visible = visible;
+            unsafe { nuuroWasmSetCursorVisible(if visible { 1 } else { 0 }) };
+        }
+    }
+
+    fn resolve_text_input(&mut self) {
+        let active = self.ctx.is_text_input_active();
+        if active != self.text_input_active {
+            self.text_input_active = active;
+            unsafe { nuuroWasmSetTextInput(if active { 1 } else { 0 }) };
+        }
+    }
+
     fn update_cookie(&mut self) {
         if self.ctx.take_cookie_updated_flag() {
             let cookie = self.ctx.cookie_buffer();
@@ -128,6 +286,20 @@ impl<AS: AppAssetId, AP: App<AS>> AppRunner<AS, AP> {
             }
         }
     }
+
+    // Applies the requests made by the app during a callback, returns false if it should close
+    fn finish_callback(&mut self) -> bool {
+        self.update_cookie();
+        self.resolve_text_input();
+        self.resolve_cursor_visibility();
+        if self.ctx.take_close_request() {
+            false
+        } else {
+            self.resolve_fullscreen_requests();
+            self.resolve_pointer_lock_requests();
+            true
+        }
+    }
 }
 
 impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
@@ -150,9 +322,8 @@ impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
             self.ctx.set_dims(renderer.app_dims(), renderer.native_px());
             self.app.start(&mut self.ctx);
         }
-        self.update_cookie();
         assert!(
-            !self.ctx.take_close_request(),
+            self.finish_callback(),
             "unexpected close immediately upon start"
         );
     }
@@ -165,23 +336,36 @@ impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
 
     fn update_and_draw(&mut self, time_sec: f64) -> bool {
         self.update_is_fullscreen();
+        self.update_is_pointer_locked();
         let elapsed = self
             .last_time_sec
             .map(|x| time_sec - x)
             .unwrap_or(0.0)
             .max(0.0)
             .min(0.1);
-        if elapsed > 0.0 {
+        if elapsed > 0.0 && !self.ctx.is_auto_paused() {
             let normalized_elapsed = elapsed.min(crate::MAX_TIMESTEP);
-            timer::update_all(normalized_elapsed);
-            self.app.advance(normalized_elapsed, &mut self.ctx);
+            let seconds = self.ctx.advance_clocks(normalized_elapsed);
+            self.app.advance(seconds, &mut self.ctx);
         }
+        self.ctx.end_frame();
         self.last_time_sec = Some(time_sec);
 
+        // fullscreen and pointer lock requests are only resolved by `finish_callback`, since
+        // browsers only grant them while handling user input
         self.update_cookie();
+        self.resolve_text_input();
+        self.resolve_cursor_visibility();
         let close_requested = self.ctx.take_close_request();
         if !close_requested {
             self.app.render(self.renderer.as_mut().unwrap(), &self.ctx);
+            if let Some(sprite_id) = self.ctx.cursor_sprite_id() {
+                let cursor = self.ctx.cursor();
+                self.renderer
+                    .as_mut()
+                    .unwrap()
+                    .draw_cursor(cursor, sprite_id);
+            }
             self.renderer.as_mut().unwrap().flush();
         }
         !close_requested
@@ -205,6 +389,7 @@ impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
 
     fn input(&mut self, key: KeyCode, down: bool) -> bool {
         self.update_is_fullscreen();
+        self.update_is_pointer_locked();
         if key == KeyCode::Touch {
             if down {
                 self.app.key_down(key, &mut self.ctx);
@@ -213,22 +398,56 @@ impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
             }
         } else {
             if down {
-                if self.held_keys.insert(key) {
+                if self.ctx.press_key(key) {
                     self.app.key_down(key, &mut self.ctx);
                 }
             } else {
-                if self.held_keys.remove(&key) {
+                if self.ctx.release_key(key) {
                     self.app.key_up(key, &mut self.ctx);
                 }
             }
         }
-        self.update_cookie();
-        if self.ctx.take_close_request() {
-            false
-        } else {
-            self.resolve_fullscreen_requests();
-            true
+        self.finish_callback()
+    }
+
+    fn music_finished(&mut self) -> bool {
+        self.app.music_finished(&mut self.ctx);
+        self.finish_callback()
+    }
+
+    fn gamepad_event(&mut self, event: GamepadEvent) -> bool {
+        gamepad::handle_event(event, &mut self.app, &mut self.ctx);
+        self.finish_callback()
+    }
+
+    fn mouse_wheel(&mut self, dx: f64, dy: f64) -> bool {
+        self.update_is_fullscreen();
+        self.update_is_pointer_locked();
+        self.app.mouse_wheel(dx, dy, &mut self.ctx);
+        self.finish_callback()
+    }
+
+    fn mouse_motion(&mut self, dx: f64, dy: f64) {
+        self.ctx.add_mouse_motion(dx, dy);
+    }
+
+    fn focus_changed(&mut self, focused: bool) -> bool {
+        set_focused(&mut self.app, &mut self.ctx, focused);
+        self.finish_callback()
+    }
+
+    fn visibility_changed(&mut self, visible: bool) -> bool {
+        set_suspended(&mut self.app, &mut self.ctx, !visible);
+        self.finish_callback()
+    }
+
+    fn text_input(&mut self, text: &str) -> bool {
+        // the text area also receives newlines and other control characters, e.g. when pasting
+        let text: String = text.chars().filter(|&c| is_text_char(c)).collect();
+        if self.ctx.is_text_input_active() && !text.is_empty() {
+            self.app.text_input(&text, &mut self.ctx);
         }
+        self.finish_callback()
     }
 
     fn music_count(&self) -> u16 {
@@ -240,34 +459,50 @@ impl<AS: AppAssetId, AP: App<AS>> TraitAppRunner for AppRunner<AS, AP> {
 
     fn on_restart(&mut self) {
         self.update_is_fullscreen();
-        for key in self.held_keys.drain() {
+        self.update_is_pointer_locked();
+        for key in self.ctx.release_all_keys() {
             self.app.key_up(key, &mut self.ctx);
         }
-        self.update_cookie();
+        // the javascript side reports the gamepads as connected again on the next frame
+        let gamepad_ids: Vec<_> = self.ctx.gamepads().iter().map(|pad| pad.id()).collect();
+        for id in gamepad_ids {
+            gamepad::handle_event(GamepadEvent::Disconnected(id), &mut self.app, &mut self.ctx);
+        }
         assert!(
-            !self.ctx.take_close_request(),
+            self.finish_callback(),
             "unexpected close immediately upon restart"
         );
     }
 
     fn cookie_buffer(&mut self, size: usize) -> &mut Vec<u8> {
-        self.ctx.set_cookie(vec![0; size]);
-        self.ctx.take_cookie_updated_flag();
         let buffer = self.ctx.cookie_buffer();
-        assert!(buffer.len() == size);
+        *buffer = vec![0; size];
         buffer
     }
 }
 
-pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(info: AppInfo, app: AP) {
+pub fn run<AS: 'static + AppAssetId, AP: 'static + App<AS>>(mut info: AppInfo, app: AP) {
     mark_app_created_flag();
+    let core_storage = CoreStorage::new(&info.effective_storage_name());
+    let config = Config::load(&core_storage);
+    config.apply_to_info(&mut info);
+    let mut ctx = AppContext::new(
+        CoreAudio {},
+        core_storage,
+        info.save_schema_version,
+        (0., 0.),
+        1.,
+    );
+    ctx.set_pause_when_inactive(info.pause_when_inactive);
+    ctx.set_config(config);
     *APP_RUNNER.r.borrow_mut() = Some(Box::new(AppRunner {
         app,
         info,
-        ctx: AppContext::new(CoreAudio {}, (0., 0.), 1.),
+        ctx,
         renderer: None,
         last_time_sec: None,
-        held_keys: HashSet::new(),
+        text_input_active: false,
+        cursor_visible: true,
     }));
 }
 
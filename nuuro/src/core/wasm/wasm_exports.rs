@@ -20,6 +20,7 @@
 use std::os::raw::{c_char, c_int, c_void};
 
 use super::{app_runner_borrow, app_runner_borrow_mut, app_runner_is_defined};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadEvent, GamepadId};
 use crate::input::{KeyCode, TouchPoint};
 use crate::renderer::shaders;
 
@@ -111,6 +112,20 @@ pub fn nuuroWasmMouseEvent(cursor_x: c_int, cursor_y: c_int, button: c_int, down
     }
 }
 
+pub fn nuuroWasmMouseMotion(dx: f64, dy: f64) {
+    app_runner_borrow_mut().mouse_motion(dx, dy);
+}
+
+pub fn nuuroWasmMouseWheel(cursor_x: c_int, cursor_y: c_int, dx: f64, dy: f64) -> c_int {
+    app_runner_borrow_mut().update_cursor(cursor_x as i32, cursor_y as i32);
+    let continuing = app_runner_borrow_mut().mouse_wheel(dx, dy);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
 pub fn nuuroWasmTouchEvent(touchesPos: JsInteropString, down: bool) -> c_int {
     let touchesPos = unsafe { touchesPos.into_boxed_string() };
     let touchesPos: Vec<TouchPoint> = serde_json::from_str(&touchesPos).unwrap_or(Vec::new());
@@ -126,6 +141,100 @@ pub fn nuuroWasmTouchEvent(touchesPos: JsInteropString, down: bool) -> c_int {
     }
 }
 
+pub fn nuuroWasmFocusChanged(focused: c_int) -> c_int {
+    let continuing = app_runner_borrow_mut().focus_changed(focused != 0);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmVisibilityChanged(visible: c_int) -> c_int {
+    let continuing = app_runner_borrow_mut().visibility_changed(visible != 0);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmTextInput(text: JsInteropString) -> c_int {
+    let text = unsafe { text.into_boxed_string() };
+    let continuing = app_runner_borrow_mut().text_input(&text);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmMusicFinished() -> c_int {
+    let continuing = app_runner_borrow_mut().music_finished();
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmGamepadConnected(index: c_int) -> c_int {
+    let event = GamepadEvent::Connected(GamepadId(index as usize));
+    let continuing = app_runner_borrow_mut().gamepad_event(event);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmGamepadDisconnected(index: c_int) -> c_int {
+    let event = GamepadEvent::Disconnected(GamepadId(index as usize));
+    let continuing = app_runner_borrow_mut().gamepad_event(event);
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmGamepadButton(index: c_int, button: c_int, value: f32) -> c_int {
+    assert!(button >= 0 && button <= 255);
+    let continuing = match GamepadButton::from_u8(button as u8) {
+        Some(button) => {
+            let event = GamepadEvent::Button(GamepadId(index as usize), button, value);
+            app_runner_borrow_mut().gamepad_event(event)
+        }
+        None => true,
+    };
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn nuuroWasmGamepadAxis(index: c_int, axis: c_int, value: f32) -> c_int {
+    assert!(axis >= 0 && axis <= 255);
+    let continuing = match GamepadAxis::from_u8(axis as u8) {
+        Some(axis) => {
+            // the browser reports positive Y as down, while app coordinates point up
+            let value = match axis {
+                GamepadAxis::LeftStickY | GamepadAxis::RightStickY => -value,
+                GamepadAxis::LeftStickX | GamepadAxis::RightStickX => value,
+            };
+            let event = GamepadEvent::Axis(GamepadId(index as usize), axis, value);
+            app_runner_borrow_mut().gamepad_event(event)
+        }
+        None => true,
+    };
+    if continuing {
+        1
+    } else {
+        0
+    }
+}
+
 pub fn nuuroWasmIsAppDefined() -> c_int {
     if app_runner_is_defined() {
         1
@@ -221,6 +330,21 @@ macro_rules! nuuro_header {
                 ::nuuro::wasm_exports::nuuroWasmMouseEvent(cursor_x, cursor_y, button, down)
             }
 
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmMouseMotion(dx: f64, dy: f64) {
+                ::nuuro::wasm_exports::nuuroWasmMouseMotion(dx, dy)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmMouseWheel(
+                cursor_x: c_int,
+                cursor_y: c_int,
+                dx: f64,
+                dy: f64,
+            ) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmMouseWheel(cursor_x, cursor_y, dx, dy)
+            }
+
             #[no_mangle]
             pub unsafe extern "C" fn nuuroWasmTouchEvent(
                 touchesPos: JsInteropString,
@@ -229,6 +353,54 @@ macro_rules! nuuro_header {
                 ::nuuro::wasm_exports::nuuroWasmTouchEvent(touchesPos, down)
             }
 
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmFocusChanged(focused: c_int) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmFocusChanged(focused)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmVisibilityChanged(visible: c_int) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmVisibilityChanged(visible)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmTextInput(text: JsInteropString) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmTextInput(text)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmMusicFinished() -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmMusicFinished()
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmGamepadConnected(index: c_int) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmGamepadConnected(index)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmGamepadDisconnected(index: c_int) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmGamepadDisconnected(index)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmGamepadButton(
+                index: c_int,
+                button: c_int,
+                value: f32,
+            ) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmGamepadButton(index, button, value)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn nuuroWasmGamepadAxis(
+                index: c_int,
+                axis: c_int,
+                value: f32,
+            ) -> c_int {
+                ::nuuro::wasm_exports::nuuroWasmGamepadAxis(index, axis, value)
+            }
+
             #[no_mangle]
             pub unsafe extern "C" fn nuuroWasmIsAppDefined() -> c_int {
                 ::nuuro::wasm_exports::nuuroWasmIsAppDefined()
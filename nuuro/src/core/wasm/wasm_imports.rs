@@ -27,6 +27,11 @@ extern "C" {
     pub fn nuuroWasmPlayMusic(id: c_int, volume: f32);
     pub fn nuuroWasmLoopMusic(id: c_int, volume: f32);
     pub fn nuuroWasmStopMusic();
+    pub fn nuuroWasmIsAudioAvailable() -> c_int;
+    pub fn nuuroWasmSetMusicPaused(paused: c_int);
+    pub fn nuuroWasmSetMusicVolume(volume: f32);
+    pub fn nuuroWasmAddSynthSound(size: usize, data: *const c_void, sample_rate: c_int) -> c_int;
+    pub fn nuuroWasmPlaySynthSound(id: c_int, volume: f32);
 
     pub fn nuuroWasmSpriteAtlasBinSize() -> usize;
     pub fn nuuroWasmSpriteAtlasBinFill(buffer: *mut c_void);
@@ -37,7 +42,25 @@ extern "C" {
     pub fn nuuroWasmCancelFullscreen();
     pub fn nuuroWasmIsFullscreen() -> c_int;
 
+    pub fn nuuroWasmSetTextInput(active: c_int);
+
+    pub fn nuuroWasmSetCursorVisible(visible: c_int);
+    pub fn nuuroWasmRequestPointerLock();
+    pub fn nuuroWasmExitPointerLock();
+    pub fn nuuroWasmIsPointerLocked() -> c_int;
+
     pub fn nuuroWasmWriteCookie(size: usize, data: *const c_void);
 
+    pub fn nuuroWasmStorageRead(key_size: usize, key: *const c_void) -> c_int;
+    pub fn nuuroWasmStorageWrite(
+        key_size: usize,
+        key: *const c_void,
+        size: usize,
+        data: *const c_void,
+    ) -> c_int;
+    pub fn nuuroWasmStorageRemove(key_size: usize, key: *const c_void) -> c_int;
+    pub fn nuuroWasmStorageList(prefix_size: usize, prefix: *const c_void) -> c_int;
+    pub fn nuuroWasmStorageResultFill(buffer: *mut c_void);
+
     pub fn nuuroWasmConsoleLog(string: *mut c_char);
 }
@@ -0,0 +1,472 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lightweight entity storage for game objects.
+//!
+//! A `World` holds entities, which are plain ids, and components of any type attached
+//! to them. Entity ids are generational: once an entity is despawned its id stays
+//! invalid, even after the slot is reused by a new entity.
+//! Systems are ordinary functions iterating over the entities having some components,
+//! e.g. with `World::query2_mut`.
+//!
+//! The `Transform` and `Sprite` components, drawn by `render_sprites`, cover the common
+//! case of objects drawn as a single sprite.
+//!
+//! ```
+//! use nuuro::ecs::World;
+//!
+//! struct Position(f64, f64);
+//! struct Velocity(f64, f64);
+//!
+//! let mut world = World::new();
+//! let ball = world.spawn();
+//! world.insert(ball, Position(0., 0.));
+//! world.insert(ball, Velocity(2., 1.));
+//! let wall = world.spawn();
+//! world.insert(wall, Position(10., 0.));
+//!
+//! for (_, pos, vel) in world.query2_mut::<Position, Velocity>() {
+//!     pos.0 += vel.0;
+//!     pos.1 += vel.1;
+//! }
+//! assert_eq!(world.get::<Position>(ball).unwrap().0, 2.);
+//!
+//! world.despawn(ball);
+//! assert!(!world.is_alive(ball));
+//! assert_eq!(world.query::<Position>().count(), 1);
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::asset_id::{AppAssetId, IdU16};
+use crate::renderer::{Affine, SpriteRenderer, Vec2};
+
+/// Id of an entity in a `World`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+trait ComponentStore {
+    fn remove_index(&mut self, index: usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// Components of one type, indexed by entity index
+struct Components<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T: 'static> ComponentStore for Components<T> {
+    fn remove_index(&mut self, index: usize) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            *slot = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Collection of entities and their components.
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    components: HashMap<TypeId, Box<dyn ComponentStore>>,
+}
+
+impl World {
+    /// Creates an empty world.
+    pub fn new() -> World {
+        World::default()
+    }
+
+    /// Creates a new entity without components.
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                (self.generations.len() - 1) as u32
+            }
+        };
+        self.alive[index as usize] = true;
+        Entity {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    /// Removes `entity` and all its components, returning `false` if it was not alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let index = entity.index as usize;
+        for store in self.components.values_mut() {
+            store.remove_index(index);
+        }
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    /// Returns `true` if `entity` was spawned and not despawned yet.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len()
+            && self.alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    /// Returns the number of alive entities.
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    /// Returns `true` if there are no alive entities.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Despawns every entity.
+    pub fn clear(&mut self) {
+        let entities: Vec<Entity> = self.entities().collect();
+        for entity in entities {
+            self.despawn(entity);
+        }
+    }
+
+    /// Iterates over all alive entities.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|&(_, &alive)| alive)
+            .map(move |(index, _)| self.entity_at(index))
+    }
+
+    fn entity_at(&self, index: usize) -> Entity {
+        entity(&self.generations, index)
+    }
+
+    fn store<T: 'static>(&self) -> Option<&Components<T>> {
+        self.components
+            .get(&TypeId::of::<T>())
+            .and_then(|store| store.as_any().downcast_ref())
+    }
+
+    fn store_mut<T: 'static>(&mut self) -> Option<&mut Components<T>> {
+        self.components
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|store| store.as_any_mut().downcast_mut())
+    }
+
+    /// Attaches `component` to `entity`, returning the component of the same type
+    /// it replaces, if any.
+    ///
+    /// Does nothing if `entity` is not alive.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let store = self
+            .components
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Components::<T> { slots: Vec::new() }))
+            .as_any_mut()
+            .downcast_mut::<Components<T>>()
+            .unwrap();
+        let index = entity.index as usize;
+        if store.slots.len() <= index {
+            store.slots.resize_with(index + 1, || None);
+        }
+        store.slots[index].replace(component)
+    }
+
+    /// Detaches the component of type `T` from `entity`, returning it.
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.store_mut::<T>()
+            .and_then(|store| store.slots.get_mut(entity.index as usize))
+            .and_then(|slot| slot.take())
+    }
+
+    /// Returns the component of type `T` attached to `entity`.
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.store::<T>()
+            .and_then(|store| store.slots.get(entity.index as usize))
+            .and_then(|slot| slot.as_ref())
+    }
+
+    /// Returns the component of type `T` attached to `entity`, mutably.
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.store_mut::<T>()
+            .and_then(|store| store.slots.get_mut(entity.index as usize))
+            .and_then(|slot| slot.as_mut())
+    }
+
+    /// Returns `true` if a component of type `T` is attached to `entity`.
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    /// Iterates over the entities having a component of type `T`.
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> + '_ {
+        self.store::<T>()
+            .into_iter()
+            .flat_map(|store| store.slots.iter().enumerate())
+            .filter_map(move |(index, slot)| {
+                slot.as_ref()
+                    .map(|component| (self.entity_at(index), component))
+            })
+    }
+
+    /// Iterates over the entities having a component of type `T`, mutably.
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> + '_ {
+        let generations = &self.generations;
+        self.components
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|store| store.as_any_mut().downcast_mut::<Components<T>>())
+            .into_iter()
+            .flat_map(|store| store.slots.iter_mut().enumerate())
+            .filter_map(move |(index, slot)| {
+                slot.as_mut()
+                    .map(|component| (entity(generations, index), component))
+            })
+    }
+
+    /// Iterates over the entities having components of both type `T` and type `U`.
+    pub fn query2<T: 'static, U: 'static>(&self) -> impl Iterator<Item = (Entity, &T, &U)> + '_ {
+        let others = self.store::<U>();
+        self.query::<T>().filter_map(move |(entity, a)| {
+            others
+                .and_then(|store| store.slots.get(entity.index as usize))
+                .and_then(|slot| slot.as_ref())
+                .map(|b| (entity, a, b))
+        })
+    }
+
+    /// Iterates over the entities having components of both type `T` and type `U`, mutably.
+    ///
+    /// Panics if `T` and `U` are the same type.
+    pub fn query2_mut<T: 'static, U: 'static>(
+        &mut self,
+    ) -> impl Iterator<Item = (Entity, &mut T, &mut U)> + '_ {
+        assert!(
+            TypeId::of::<T>() != TypeId::of::<U>(),
+            "query2_mut requires two different component types"
+        );
+        let generations = &self.generations;
+        let (mut a, mut b) = (None, None);
+        for (&type_id, store) in self.components.iter_mut() {
+            if type_id == TypeId::of::<T>() {
+                a = store.as_any_mut().downcast_mut::<Components<T>>();
+            } else if type_id == TypeId::of::<U>() {
+                b = store.as_any_mut().downcast_mut::<Components<U>>();
+            }
+        }
+        let stores = a.zip(b);
+        stores
+            .into_iter()
+            .flat_map(|(a, b)| a.slots.iter_mut().zip(b.slots.iter_mut()).enumerate())
+            .filter_map(move |(index, slots)| match slots {
+                (Some(a), Some(b)) => Some((entity(generations, index), a, b)),
+                _ => None,
+            })
+    }
+}
+
+fn entity(generations: &[u32], index: usize) -> Entity {
+    Entity {
+        index: index as u32,
+        generation: generations[index],
+    }
+}
+
+/// Position, rotation and scale of an entity, used by `render_sprites`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    /// Position in app pixels.
+    pub pos: Vec2,
+    /// Counter-clockwise rotation in radians.
+    pub rotation: f64,
+    /// Scale of the x and y axes.
+    pub scale: Vec2,
+}
+
+impl Transform {
+    /// Returns a transform placing an entity at `(x, y)`, without rotation or scaling.
+    pub fn at(x: f64, y: f64) -> Transform {
+        Transform {
+            pos: Vec2::new(x, y),
+            rotation: 0.,
+            scale: Vec2::new(1., 1.),
+        }
+    }
+
+    /// Returns the transformation, scaling first, then rotating and translating.
+    pub fn affine(&self) -> Affine {
+        Affine::translate(self.pos.x, self.pos.y)
+            .pre_rotate(self.rotation)
+            .pre_scale_axes(self.scale.x, self.scale.y)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::at(0., 0.)
+    }
+}
+
+/// Sprite drawn at the `Transform` of an entity by `render_sprites`.
+pub struct Sprite<A: AppAssetId> {
+    id: u16,
+    /// Sprites with a higher layer are drawn over those with a lower one (default is `0`).
+    pub layer: i32,
+    /// Blending with the color white, see `SpriteRenderer::draw_flash` (default is `0.0`).
+    pub flash_ratio: f64,
+    /// Whether the sprite is drawn (default is `true`).
+    pub visible: bool,
+    phantom: PhantomData<A>,
+}
+
+impl<A: AppAssetId> Sprite<A> {
+    /// Returns a visible sprite component on layer `0`.
+    pub fn new(sprite: A::Sprite) -> Sprite<A> {
+        Sprite {
+            id: sprite.id_u16(),
+            layer: 0,
+            flash_ratio: 0.,
+            visible: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the layer, see `Sprite::layer`.
+    pub fn layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Changes the drawn sprite.
+    pub fn set_sprite(&mut self, sprite: A::Sprite) {
+        self.id = sprite.id_u16();
+    }
+}
+
+/// Draws the `Sprite` of every entity having a `Transform`, ordered by layer.
+///
+/// Entities on the same layer are drawn in a stable order.
+pub fn render_sprites<A: AppAssetId + 'static>(world: &World, renderer: &mut SpriteRenderer<A>) {
+    let mut sprites: Vec<(&Transform, &Sprite<A>)> = world
+        .query2::<Transform, Sprite<A>>()
+        .filter(|(_, _, sprite)| sprite.visible)
+        .map(|(_, transform, sprite)| (transform, sprite))
+        .collect();
+    sprites.sort_by_key(|(_, sprite)| sprite.layer);
+    for (transform, sprite) in sprites {
+        renderer.draw_id(&transform.affine(), sprite.id, sprite.flash_ratio);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn stale_entity_is_rejected_after_its_index_is_reused() {
+        let mut world = World::new();
+        let stale = world.spawn();
+        world.insert(stale, 1u32);
+        assert!(world.despawn(stale));
+
+        let reused = world.spawn();
+        assert_eq!(reused.index, stale.index);
+        assert_ne!(reused, stale);
+        assert!(!world.is_alive(stale));
+        assert!(world.is_alive(reused));
+        assert_eq!(world.get::<u32>(reused), None);
+
+        world.insert(reused, 2u32);
+        assert_eq!(world.get::<u32>(stale), None);
+        assert_eq!(world.insert(stale, 3u32), None);
+        assert_eq!(world.remove::<u32>(stale), None);
+        assert!(!world.despawn(stale));
+        assert_eq!(world.get::<u32>(reused), Some(&2));
+        assert_eq!(world.len(), 1);
+    }
+
+    #[test]
+    fn insert_on_dead_entity_drops_the_component() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.despawn(entity);
+
+        let component = Rc::new(());
+        assert!(world.insert(entity, component.clone()).is_none());
+        assert_eq!(Rc::strong_count(&component), 1);
+        assert_eq!(world.query::<Rc<()>>().count(), 0);
+    }
+
+    #[test]
+    fn query2_mut_yields_entities_having_both_components() {
+        let mut world = World::new();
+        let both = world.spawn();
+        let only_first = world.spawn();
+        let only_second = world.spawn();
+        let despawned = world.spawn();
+        world.insert(both, 1u32);
+        world.insert(both, 'a');
+        world.insert(only_first, 2u32);
+        world.insert(only_second, 'b');
+        world.insert(despawned, 3u32);
+        world.insert(despawned, 'c');
+        world.despawn(despawned);
+
+        let mut found = Vec::new();
+        for (entity, number, letter) in world.query2_mut::<u32, char>() {
+            *number += 10;
+            *letter = letter.to_ascii_uppercase();
+            found.push(entity);
+        }
+        assert_eq!(found, vec![both]);
+        assert_eq!(world.get::<u32>(both), Some(&11));
+        assert_eq!(world.get::<char>(both), Some(&'A'));
+        assert_eq!(world.get::<u32>(only_first), Some(&2));
+        assert_eq!(world.query2_mut::<u32, String>().count(), 0);
+    }
+}
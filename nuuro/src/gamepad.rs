@@ -0,0 +1,268 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Gamepad input.
+//!
+//! Connected gamepads can be queried with `AppContext::gamepads()`, and button presses
+//! are also delivered through the `App::gamepad_button_down` and `App::gamepad_button_up`
+//! callbacks. Buttons and axes follow the
+//! [standard gamepad layout](https://www.w3.org/TR/gamepad/#remapping) on every target.
+
+use crate::asset_id::AppAssetId;
+use crate::{App, AppContext};
+
+pub(crate) const BUTTON_COUNT: usize = GamepadButton::Mode as usize + 1;
+pub(crate) const AXIS_COUNT: usize = GamepadAxis::RightStickY as usize + 1;
+
+const BUTTON_PRESS_THRESHOLD: f32 = 0.5;
+const DEFAULT_DEADZONE: f32 = 0.15;
+
+/// Identifies a connected gamepad.
+///
+/// Ids are reused when a gamepad is disconnected and connected again.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GamepadId(pub(crate) usize);
+
+/// Gamepad buttons, named after their position on the standard layout.
+///
+/// The face buttons are `South`, `East`, `West` and `North` (A, B, X and Y on an
+/// Xbox controller). `LeftTrigger` and `RightTrigger` are analog on most gamepads,
+/// use `Gamepad::button_value` to read how far they are pressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Mode,
+}
+
+impl GamepadButton {
+    pub(crate) fn from_u8(index: u8) -> Option<GamepadButton> {
+        if (index as usize) < BUTTON_COUNT {
+            Some(unsafe { std::mem::transmute::<u8, GamepadButton>(index) })
+        } else {
+            None
+        }
+    }
+}
+
+/// Analog stick axes of a gamepad.
+///
+/// Values lie in the range `-1` to `1`, positive X is right and positive Y is up,
+/// matching app coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+impl GamepadAxis {
+    pub(crate) fn from_u8(index: u8) -> Option<GamepadAxis> {
+        if (index as usize) < AXIS_COUNT {
+            Some(unsafe { std::mem::transmute::<u8, GamepadAxis>(index) })
+        } else {
+            None
+        }
+    }
+}
+
+/// Current state of a connected gamepad.
+#[derive(Debug, Clone)]
+pub struct Gamepad {
+    id: GamepadId,
+    buttons: [f32; BUTTON_COUNT],
+    axes: [f32; AXIS_COUNT],
+    deadzone: f32,
+}
+
+impl Gamepad {
+    fn new(id: GamepadId, deadzone: f32) -> Gamepad {
+        Gamepad {
+            id,
+            buttons: [0.; BUTTON_COUNT],
+            axes: [0.; AXIS_COUNT],
+            deadzone,
+        }
+    }
+
+    /// Returns the id of this gamepad.
+    pub fn id(&self) -> GamepadId {
+        self.id
+    }
+
+    /// Checks whether the given button is held down.
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.button_value(button) > BUTTON_PRESS_THRESHOLD
+    }
+
+    /// Returns how far the given button is pressed, in the range `0` to `1`.
+    ///
+    /// Digital buttons are always either `0` or `1`.
+    pub fn button_value(&self, button: GamepadButton) -> f32 {
+        self.buttons[button as usize]
+    }
+
+    /// Returns the value of the given axis, in the range `-1` to `1`.
+    ///
+    /// Values within the deadzone read as `0`, and the remaining range is rescaled
+    /// so that the output still reaches `-1` and `1`.
+    /// See also `AppContext::set_gamepad_deadzone`.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.axes[axis as usize];
+        let magnitude = apply_deadzone(value.abs(), self.deadzone);
+        magnitude.copysign(value)
+    }
+
+    /// Returns the `(x, y)` position of the left stick.
+    ///
+    /// Unlike `axis`, the deadzone is applied to the distance from the center,
+    /// which feels better for movement in arbitrary directions.
+    pub fn left_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    /// Returns the `(x, y)` position of the right stick.
+    ///
+    /// See `left_stick` for details on how the deadzone is applied.
+    pub fn right_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    fn stick(&self, x_axis: GamepadAxis, y_axis: GamepadAxis) -> (f32, f32) {
+        let (x, y) = (self.axes[x_axis as usize], self.axes[y_axis as usize]);
+        let len = (x * x + y * y).sqrt();
+        if len == 0. {
+            return (0., 0.);
+        }
+        let scale = apply_deadzone(len.min(1.), self.deadzone) / len;
+        (x * scale, y * scale)
+    }
+}
+
+fn apply_deadzone(magnitude: f32, deadzone: f32) -> f32 {
+    if magnitude <= deadzone {
+        0.
+    } else {
+        ((magnitude - deadzone) / (1. - deadzone)).min(1.)
+    }
+}
+
+pub(crate) enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    Button(GamepadId, GamepadButton, f32),
+    Axis(GamepadId, GamepadAxis, f32),
+}
+
+pub(crate) struct Gamepads {
+    pads: Vec<Gamepad>,
+    deadzone: f32,
+}
+
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        Gamepads {
+            pads: Vec::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+
+    pub fn all(&self) -> &[Gamepad] {
+        &self.pads
+    }
+
+    pub fn get(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.pads.iter().find(|pad| pad.id == id)
+    }
+
+    fn get_mut(&mut self, id: GamepadId) -> Option<&mut Gamepad> {
+        self.pads.iter_mut().find(|pad| pad.id == id)
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone;
+        for pad in &mut self.pads {
+            pad.deadzone = deadzone;
+        }
+    }
+}
+
+/// Applies a gamepad event to the state in `ctx` and invokes the matching `App` callbacks.
+pub(crate) fn handle_event<AS: AppAssetId, AP: App<AS>>(
+    event: GamepadEvent,
+    app: &mut AP,
+    ctx: &mut AppContext<AS>,
+) {
+    match event {
+        GamepadEvent::Connected(id) => {
+            if ctx.gamepads.get(id).is_none() {
+                let deadzone = ctx.gamepads.deadzone;
+                ctx.gamepads.pads.push(Gamepad::new(id, deadzone));
+                app.gamepad_connected(id, ctx);
+            }
+        }
+        GamepadEvent::Disconnected(id) => {
+            // buttons held while unplugging are released first, so none is left stuck down
+            let held: Vec<GamepadButton> = match ctx.gamepads.get_mut(id) {
+                Some(pad) => {
+                    let held = (0..BUTTON_COUNT as u8)
+                        .filter_map(GamepadButton::from_u8)
+                        .filter(|&button| pad.is_pressed(button))
+                        .collect();
+                    pad.buttons = [0.; BUTTON_COUNT];
+                    held
+                }
+                None => return,
+            };
+            for button in held {
+                app.gamepad_button_up(id, button, ctx);
+            }
+            ctx.gamepads.pads.retain(|pad| pad.id != id);
+            app.gamepad_disconnected(id, ctx);
+        }
+        GamepadEvent::Button(id, button, value) => {
+            if let Some(pad) = ctx.gamepads.get_mut(id) {
+                let was_pressed = pad.is_pressed(button);
+                pad.buttons[button as usize] = value.clamp(0., 1.);
+                match (was_pressed, pad.is_pressed(button)) {
+                    (false, true) => app.gamepad_button_down(id, button, ctx),
+                    (true, false) => app.gamepad_button_up(id, button, ctx),
+                    (false, false) | (true, true) => {}
+                }
+            }
+        }
+        GamepadEvent::Axis(id, axis, value) => {
+            if let Some(pad) = ctx.gamepads.get_mut(id) {
+                pad.axes[axis as usize] = value.clamp(-1., 1.);
+            }
+        }
+    }
+}
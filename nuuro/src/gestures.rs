@@ -0,0 +1,364 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Touch gesture recognition on top of `AppContext::touches()`.
+//!
+//! A `GestureRecognizer` is updated once per frame from `App::advance`, after which
+//! the per-touch events and recognized gestures of that frame can be inspected:
+//!
+//! ```rust,no_run
+//! use nuuro::asset_id::AppAssetId;
+//! use nuuro::gestures::{Gesture, GestureRecognizer};
+//! use nuuro::renderer::Renderer;
+//! use nuuro::{App, AppContext, KeyCode};
+//!
+//! struct MapView {
+//!     gestures: GestureRecognizer,
+//!     selected: Option<(f64, f64)>,
+//!     zoom: f64,
+//! }
+//!
+//! impl<A: AppAssetId> App<A> for MapView {
+//!     fn advance(&mut self, seconds: f64, ctx: &mut AppContext<A>) {
+//!         self.gestures.update(seconds, ctx);
+//!         for gesture in self.gestures.gestures() {
+//!             match *gesture {
+//!                 Gesture::Tap { pos } => self.selected = Some(pos),
+//!                 Gesture::Pinch { scale, .. } => self.zoom *= scale,
+//!                 _ => {}
+//!             }
+//!         }
+//!     }
+//!
+//!     fn key_down(&mut self, _key: KeyCode, _ctx: &mut AppContext<A>) {}
+//!
+//!     fn render(&mut self, _renderer: &mut Renderer<A>, _ctx: &AppContext<A>) {}
+//! }
+//! ```
+
+use crate::asset_id::AppAssetId;
+use crate::AppContext;
+
+/// Stage of a single touch in its lifetime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TouchPhase {
+    Began,
+    Moved,
+    Ended,
+}
+
+/// Change of a single touch since the previous update.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TouchEvent {
+    /// Identifier of the touch, as in `TouchPoint::id()`.
+    pub id: u32,
+    pub phase: TouchPhase,
+    /// Position in app coordinates; for `TouchPhase::Ended` this is the last known position.
+    pub pos: (f64, f64),
+}
+
+/// Direction of a swipe, in app coordinates (`Up` is towards positive y).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Gesture recognized by a `GestureRecognizer`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Gesture {
+    /// A short touch that barely moved.
+    Tap { pos: (f64, f64) },
+    /// A second tap close in time and space to a previous one.
+    /// It is reported instead of a second `Tap`.
+    DoubleTap { pos: (f64, f64) },
+    /// A touch held still for a while, reported once while the touch is still down.
+    LongPress { pos: (f64, f64) },
+    /// A quick, mostly straight movement of a single touch.
+    Swipe {
+        direction: SwipeDirection,
+        start: (f64, f64),
+        end: (f64, f64),
+    },
+    /// Two touches moving closer or apart. `scale` is the change in distance between
+    /// them since the previous update, so it can be multiplied into a zoom factor.
+    Pinch { center: (f64, f64), scale: f64 },
+}
+
+#[derive(Clone)]
+struct TrackedTouch {
+    id: u32,
+    start_pos: (f64, f64),
+    start_time: f64,
+    pos: (f64, f64),
+    moved_away: bool,
+    multi: bool,
+    long_pressed: bool,
+}
+
+/// Recognizes gestures and per-touch events from the touches in `AppContext`.
+///
+/// Distances are in app coordinates and durations in seconds.
+#[derive(Clone)]
+pub struct GestureRecognizer {
+    tap_max_distance: f64,
+    tap_max_duration: f64,
+    double_tap_interval: f64,
+    long_press_duration: f64,
+    swipe_min_distance: f64,
+    swipe_max_duration: f64,
+    time: f64,
+    touches: Vec<TrackedTouch>,
+    last_tap: Option<(f64, (f64, f64))>,
+    // ids of the two touches being pinched, and their distance
+    pinch: Option<((u32, u32), f64)>,
+    events: Vec<TouchEvent>,
+    gestures: Vec<Gesture>,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> GestureRecognizer {
+        GestureRecognizer {
+            tap_max_distance: 8.,
+            tap_max_duration: 0.3,
+            double_tap_interval: 0.3,
+            long_press_duration: 0.5,
+            swipe_min_distance: 30.,
+            swipe_max_duration: 0.5,
+            time: 0.,
+            touches: Vec::new(),
+            last_tap: None,
+            pinch: None,
+            events: Vec::new(),
+            gestures: Vec::new(),
+        }
+    }
+}
+
+impl GestureRecognizer {
+    /// Creates a recognizer with default thresholds.
+    pub fn new() -> GestureRecognizer {
+        GestureRecognizer::default()
+    }
+
+    /// Sets how far a touch may move and still count as a tap or long press,
+    /// defaults to `8`.
+    pub fn tap_max_distance(mut self, distance: f64) -> Self {
+        assert!(distance >= 0., "unrealistic tap distance: {}", distance);
+        self.tap_max_distance = distance;
+        self
+    }
+
+    /// Sets the longest touch that counts as a tap, defaults to `0.3` seconds.
+    pub fn tap_max_duration(mut self, seconds: f64) -> Self {
+        assert!(seconds > 0., "unrealistic tap duration: {}", seconds);
+        self.tap_max_duration = seconds;
+        self
+    }
+
+    /// Sets the longest pause between two taps of a double tap, defaults to `0.3` seconds.
+    pub fn double_tap_interval(mut self, seconds: f64) -> Self {
+        assert!(
+            seconds >= 0.,
+            "unrealistic double tap interval: {}",
+            seconds
+        );
+        self.double_tap_interval = seconds;
+        self
+    }
+
+    /// Sets how long a touch must be held still for a long press, defaults to `0.5` seconds.
+    pub fn long_press_duration(mut self, seconds: f64) -> Self {
+        assert!(seconds > 0., "unrealistic long press duration: {}", seconds);
+        self.long_press_duration = seconds;
+        self
+    }
+
+    /// Sets the shortest distance and longest duration of a swipe,
+    /// defaults to `30` and `0.5` seconds.
+    pub fn swipe(mut self, min_distance: f64, max_duration: f64) -> Self {
+        assert!(
+            min_distance > 0.,
+            "unrealistic swipe distance: {}",
+            min_distance
+        );
+        assert!(
+            max_duration > 0.,
+            "unrealistic swipe duration: {}",
+            max_duration
+        );
+        self.swipe_min_distance = min_distance;
+        self.swipe_max_duration = max_duration;
+        self
+    }
+
+    /// Returns the per-touch events found by the last `update`.
+    pub fn touch_events(&self) -> &[TouchEvent] {
+        &self.events
+    }
+
+    /// Returns the gestures recognized by the last `update`.
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+
+    /// Compares the touches in `ctx` to those of the previous update and recognizes gestures.
+    ///
+    /// This should be called once per `App::advance`, passing the elapsed seconds.
+    pub fn update<A: AppAssetId>(&mut self, seconds: f64, ctx: &AppContext<A>) {
+        self.time += seconds;
+        self.events.clear();
+        self.gestures.clear();
+
+        let current = ctx.touches();
+
+        let mut index = 0;
+        while index < self.touches.len() {
+            if current.iter().any(|t| t.id() == self.touches[index].id) {
+                index += 1;
+            } else {
+                let touch = self.touches.remove(index);
+                self.events.push(TouchEvent {
+                    id: touch.id,
+                    phase: TouchPhase::Ended,
+                    pos: touch.pos,
+                });
+                self.touch_ended(&touch);
+            }
+        }
+
+        for point in current {
+            let pos = point.pos();
+            let time = self.time;
+            let tap_max_distance = self.tap_max_distance;
+            match self.touches.iter_mut().find(|t| t.id == point.id()) {
+                Some(touch) => {
+                    if touch.pos != pos {
+                        touch.pos = pos;
+                        touch.moved_away |= distance(touch.start_pos, pos) > tap_max_distance;
+                        self.events.push(TouchEvent {
+                            id: touch.id,
+                            phase: TouchPhase::Moved,
+                            pos,
+                        });
+                    }
+                }
+                None => {
+                    self.touches.push(TrackedTouch {
+                        id: point.id(),
+                        start_pos: pos,
+                        start_time: time,
+                        pos,
+                        moved_away: false,
+                        multi: false,
+                        long_pressed: false,
+                    });
+                    self.events.push(TouchEvent {
+                        id: point.id(),
+                        phase: TouchPhase::Began,
+                        pos,
+                    });
+                }
+            }
+        }
+
+        if self.touches.len() > 1 {
+            for touch in &mut self.touches {
+                touch.multi = true;
+            }
+        }
+
+        for touch in &mut self.touches {
+            if !touch.multi
+                && !touch.moved_away
+                && !touch.long_pressed
+                && self.time - touch.start_time >= self.long_press_duration
+            {
+                touch.long_pressed = true;
+                self.gestures.push(Gesture::LongPress { pos: touch.pos });
+            }
+        }
+
+        self.update_pinch();
+    }
+
+    fn touch_ended(&mut self, touch: &TrackedTouch) {
+        if touch.multi || touch.long_pressed {
+            return;
+        }
+        let duration = self.time - touch.start_time;
+        let moved = distance(touch.start_pos, touch.pos);
+        if !touch.moved_away && duration <= self.tap_max_duration {
+            let pos = touch.pos;
+            let is_double = self.last_tap.is_some_and(|(time, last_pos)| {
+                self.time - time <= self.double_tap_interval
+                    && distance(last_pos, pos) <= 2. * self.tap_max_distance
+            });
+            if is_double {
+                self.last_tap = None;
+                self.gestures.push(Gesture::DoubleTap { pos });
+            } else {
+                self.last_tap = Some((self.time, pos));
+                self.gestures.push(Gesture::Tap { pos });
+            }
+        } else if moved >= self.swipe_min_distance && duration <= self.swipe_max_duration {
+            let (dx, dy) = (
+                touch.pos.0 - touch.start_pos.0,
+                touch.pos.1 - touch.start_pos.1,
+            );
+            let direction = if dx.abs() >= dy.abs() {
+                if dx > 0. {
+                    SwipeDirection::Right
+                } else {
+                    SwipeDirection::Left
+                }
+            } else if dy > 0. {
+                SwipeDirection::Up
+            } else {
+                SwipeDirection::Down
+            };
+            self.gestures.push(Gesture::Swipe {
+                direction,
+                start: touch.start_pos,
+                end: touch.pos,
+            });
+        }
+    }
+
+    fn update_pinch(&mut self) {
+        if self.touches.len() < 2 {
+            self.pinch = None;
+            return;
+        }
+        let ids = (self.touches[0].id, self.touches[1].id);
+        let (a, b) = (self.touches[0].pos, self.touches[1].pos);
+        let current = distance(a, b);
+        // a different pair of touches starts a new pinch instead of jumping in scale
+        if let Some((previous_ids, previous)) = self.pinch {
+            if previous_ids == ids && previous > 0. && current != previous {
+                self.gestures.push(Gesture::Pinch {
+                    center: ((a.0 + b.0) / 2., (a.1 + b.1) / 2.),
+                    scale: current / previous,
+                });
+            }
+        }
+        self.pinch = Some((ids, current));
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
@@ -14,9 +14,10 @@
 
 //! Structs related to user input.
 
+use std::collections::HashSet;
+
 #[cfg(target_arch = "wasm32")]
 use serde::{Deserialize, Serialize};
-#[cfg(target_arch = "wasm32")]
 use std::mem;
 
 /// Enum for keyboard keys and mouse buttons.
@@ -68,12 +69,107 @@ pub enum KeyCode {
     Space,
     Backspace,
     Delete,
+    Tab,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    CapsLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    LMeta,
+    RMeta,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+    Backslash,
+    Semicolon,
+    Apostrophe,
+    Comma,
+    Period,
+    Slash,
+    Grave,
     MouseLeft,
     MouseRight,
     MouseMiddle,
     Touch,
 }
 
+impl KeyCode {
+    /// Checks whether this is one of the Shift, Control, Alt or Meta keys.
+    pub fn is_modifier(self) -> bool {
+        matches!(
+            self,
+            KeyCode::LShift
+                | KeyCode::RShift
+                | KeyCode::LControl
+                | KeyCode::RControl
+                | KeyCode::LAlt
+                | KeyCode::RAlt
+                | KeyCode::LMeta
+                | KeyCode::RMeta
+        )
+    }
+}
+
+/// State of the modifier keys, returned by `AppContext::modifiers()`.
+///
+/// Each flag is set while either the left or the right key is held down.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The "Windows" key on PC and "Command" key on Mac.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    pub(crate) fn from_held_keys(held_keys: &HashSet<KeyCode>) -> Modifiers {
+        let held = |left, right| held_keys.contains(&left) || held_keys.contains(&right);
+        Modifiers {
+            shift: held(KeyCode::LShift, KeyCode::RShift),
+            ctrl: held(KeyCode::LControl, KeyCode::RControl),
+            alt: held(KeyCode::LAlt, KeyCode::RAlt),
+            meta: held(KeyCode::LMeta, KeyCode::RMeta),
+        }
+    }
+}
+
 /// Struct that store touch position
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Copy, Clone)]
@@ -123,16 +219,21 @@ impl std::fmt::Debug for TouchPoint {
     }
 }
 
-#[cfg(target_arch = "wasm32")]
 impl KeyCode {
     fn count() -> u8 {
         KeyCode::Touch as u8 + 1
     }
     pub(crate) fn from_u8(id: u8) -> Option<KeyCode> {
         if id < Self::count() {
-            Some(unsafe { mem::transmute(id) })
+            Some(unsafe { mem::transmute::<u8, KeyCode>(id) })
         } else {
             None
         }
     }
 }
+
+// Checks whether `c` can be delivered to `App::text_input`, on every target
+pub(crate) fn is_text_char(c: char) -> bool {
+    // macOS reports function and arrow keys as characters in the private use area
+    !c.is_control() && !('\u{f700}'..='\u{f8ff}').contains(&c)
+}
@@ -26,28 +26,39 @@
 //!
 //! You can start with the [nuuro template](https://github.com/juandroid007/nuuro_template).
 
-#[macro_use]
-extern crate lazy_static;
+extern crate bincode;
 extern crate byteorder;
 #[cfg(not(target_arch = "wasm32"))]
+extern crate gilrs;
+#[cfg(not(target_arch = "wasm32"))]
 extern crate glutin;
 #[cfg(not(target_arch = "wasm32"))]
 extern crate image;
 pub extern crate paste;
 #[cfg(not(target_arch = "wasm32"))]
 extern crate rodio;
-#[cfg(target_arch = "wasm32")]
 extern crate serde;
 #[cfg(target_arch = "wasm32")]
 extern crate serde_json;
+extern crate toml;
 
+pub mod actions;
 mod app_context;
 mod app_info;
 pub mod asset_id;
+pub mod collision;
+pub mod config;
 mod core;
+pub mod ecs;
+pub mod gamepad;
+pub mod gestures;
 mod input;
 pub mod renderer;
+pub mod scene;
+pub mod storage;
+pub mod synth;
 pub(crate) mod timer;
+pub mod tween;
 pub(crate) mod utils;
 
 #[cfg(target_arch = "wasm32")]
@@ -57,8 +68,12 @@ pub use crate::core::println;
 
 pub use crate::app_context::{AppContext, Audio};
 pub use crate::app_info::AppInfo;
-pub use crate::input::{KeyCode, TouchPoint};
-pub use crate::timer::Timer;
+pub use crate::config::Config;
+pub use crate::gamepad::{Gamepad, GamepadAxis, GamepadButton, GamepadId};
+pub use crate::input::{KeyCode, Modifiers, TouchPoint};
+pub use crate::renderer::Vec2;
+pub use crate::storage::{Storage, StorageError};
+pub use crate::timer::{Timer, TimerId, TimerSet, TweenId};
 
 use crate::asset_id::AppAssetId;
 use crate::renderer::Renderer;
@@ -87,6 +102,9 @@ pub trait App<A: AppAssetId> {
     fn start(&mut self, _ctx: &mut AppContext<A>) {}
 
     /// Advances the app state by a given amount of `seconds` (usually a fraction of a second).
+    ///
+    /// The `seconds` follow `AppContext::set_time_scale`, and are `0.0` while the game time
+    /// is frozen with `AppContext::pause`.
     fn advance(&mut self, seconds: f64, ctx: &mut AppContext<A>);
 
     /// Invoked when a key or mouse button is pressed down.
@@ -95,6 +113,69 @@ pub trait App<A: AppAssetId> {
     /// Invoked when a key or mouse button is released, default behavior is a no-op.
     fn key_up(&mut self, _key: KeyCode, _ctx: &mut AppContext<A>) {}
 
+    /// Invoked when the mouse wheel is scrolled, default behavior is a no-op.
+    ///
+    /// `dx` and `dy` are measured in wheel notches, so that line-based mouse wheels and
+    /// pixel-based trackpads report comparable amounts; trackpads usually produce
+    /// fractional values. Positive `dy` means scrolling up (away from the user),
+    /// positive `dx` means scrolling right.
+    fn mouse_wheel(&mut self, _dx: f64, _dy: f64, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked with text typed by the user while text input is active,
+    /// default behavior is a no-op.
+    ///
+    /// See `AppContext::start_text_input`.
+    /// The text never contains control characters such as backspace or newlines.
+    fn text_input(&mut self, _text: &str, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when the app gains or loses keyboard focus, default behavior is a no-op.
+    ///
+    /// Keys held down when focus is lost are released first, invoking `key_up`.
+    fn focus_changed(&mut self, _focused: bool, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when the app stops being visible, e.g. when the window is minimized or the
+    /// browser tab is hidden, default behavior is a no-op.
+    ///
+    /// This is a good moment to save progress, since the app may never be resumed.
+    fn suspended(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when the app becomes visible again after `suspended`,
+    /// default behavior is a no-op.
+    fn resumed(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when music started with `Audio::play_music` reaches its end,
+    /// default behavior is a no-op.
+    ///
+    /// This is not invoked for looping music, or when music is stopped or replaced.
+    fn music_finished(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when a gamepad is connected, default behavior is a no-op.
+    ///
+    /// Gamepads that are already connected when the app starts are reported
+    /// shortly after `start`.
+    fn gamepad_connected(&mut self, _id: GamepadId, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when a gamepad is disconnected, default behavior is a no-op.
+    fn gamepad_disconnected(&mut self, _id: GamepadId, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when a gamepad button is pressed, default behavior is a no-op.
+    fn gamepad_button_down(
+        &mut self,
+        _id: GamepadId,
+        _button: GamepadButton,
+        _ctx: &mut AppContext<A>,
+    ) {
+    }
+
+    /// Invoked when a gamepad button is released, default behavior is a no-op.
+    fn gamepad_button_up(
+        &mut self,
+        _id: GamepadId,
+        _button: GamepadButton,
+        _ctx: &mut AppContext<A>,
+    ) {
+    }
+
     /// Render the app in its current state.
     fn render(&mut self, renderer: &mut Renderer<A>, ctx: &AppContext<A>);
 }
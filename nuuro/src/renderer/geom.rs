@@ -12,24 +12,110 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::ops::{Add, Mul};
-
-// 2D Cartesian vector
-#[derive(Copy, Clone)]
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// 2D Cartesian vector, with the y axis pointing up.
+///
+/// Used for positions and directions by game code, the renderer and the collision module.
+///
+/// # Example
+///
+/// ```rust
+/// use nuuro::Vec2;
+///
+/// let pos = Vec2::new(3., 4.);
+/// assert_eq!(pos.len(), 5.);
+/// assert_eq!(pos + Vec2::from((1., 1.)), Vec2::new(4., 5.));
+/// assert_eq!(pos.normalize() * 10., Vec2::new(6., 8.));
+///
+/// let (x, y): (f64, f64) = pos.lerp(Vec2::zero(), 0.5).into();
+/// assert_eq!((x, y), (1.5, 2.));
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
 }
 
 impl Vec2 {
+    /// Returns the vector `(x, y)`.
     pub fn new(x: f64, y: f64) -> Vec2 {
         Vec2 { x, y }
     }
+
+    /// Returns the vector `(0, 0)`.
     pub fn zero() -> Vec2 {
         Vec2::new(0.0, 0.0)
     }
+
+    /// Returns the unit vector pointing `angle` radians counter-clockwise from the x axis.
+    pub fn from_angle(angle: f64) -> Vec2 {
+        Vec2::new(angle.cos(), angle.sin())
+    }
+
+    /// Returns the length of the vector.
     pub fn len(&self) -> f64 {
-        (self.x * self.x + self.y * self.y).sqrt()
+        self.len_squared().sqrt()
+    }
+
+    /// Returns the squared length of the vector, cheaper than `len` for comparisons.
+    pub fn len_squared(&self) -> f64 {
+        self.dot(*self)
+    }
+
+    /// Returns the distance between the points `self` and `other`.
+    pub fn distance(&self, other: Vec2) -> f64 {
+        (*self - other).len()
+    }
+
+    /// Returns the dot product of `self` and `rhs`.
+    pub fn dot(&self, rhs: Vec2) -> f64 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    /// Returns the z component of the 3D cross product of `self` and `rhs`,
+    /// positive when `rhs` is counter-clockwise from `self`.
+    pub fn cross(&self, rhs: Vec2) -> f64 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    /// Returns the vector with the same direction and a length of 1,
+    /// or the zero vector if `self` is zero.
+    pub fn normalize(&self) -> Vec2 {
+        let len = self.len();
+        if len > 0. {
+            *self / len
+        } else {
+            Vec2::zero()
+        }
+    }
+
+    /// Returns the angle in radians between the x axis and the vector, counter-clockwise,
+    /// from `-PI` to `PI`.
+    pub fn angle(&self) -> f64 {
+        self.y.atan2(self.x)
+    }
+
+    /// Returns the vector rotated counter-clockwise by `angle` radians.
+    pub fn rotate(&self, angle: f64) -> Vec2 {
+        Mat2::rotation(angle) * *self
+    }
+
+    /// Returns the linear interpolation from `self` at `t = 0.0` to `other` at `t = 1.0`.
+    pub fn lerp(&self, other: Vec2, t: f64) -> Vec2 {
+        *self + (other - *self) * t
+    }
+}
+
+impl From<(f64, f64)> for Vec2 {
+    fn from((x, y): (f64, f64)) -> Vec2 {
+        Vec2::new(x, y)
+    }
+}
+
+impl From<Vec2> for (f64, f64) {
+    fn from(v: Vec2) -> (f64, f64) {
+        (v.x, v.y)
     }
 }
 
@@ -40,6 +126,58 @@ impl Add<Vec2> for Vec2 {
     }
 }
 
+impl AddAssign<Vec2> for Vec2 {
+    fn add_assign(&mut self, rhs: Vec2) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Vec2> for Vec2 {
+    type Output = Vec2;
+    fn sub(self, rhs: Vec2) -> Vec2 {
+        Vec2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign<Vec2> for Vec2 {
+    fn sub_assign(&mut self, rhs: Vec2) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, rhs: f64) -> Vec2 {
+        Vec2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl MulAssign<f64> for Vec2 {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div<f64> for Vec2 {
+    type Output = Vec2;
+    fn div(self, rhs: f64) -> Vec2 {
+        Vec2::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl DivAssign<f64> for Vec2 {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
 // Diagonal 2D matrix
 #[derive(Copy, Clone)]
 struct Diag2 {
@@ -296,7 +434,8 @@ impl Affine {
         }
     }
 
-    pub(crate) fn apply(&self, input: Vec2) -> Vec2 {
+    /// Applies the transformation to the point `input`.
+    pub fn apply(&self, input: Vec2) -> Vec2 {
         self.mat * input + self.offset
     }
 
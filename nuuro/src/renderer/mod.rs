@@ -27,5 +27,5 @@ mod renderer;
 pub(crate) mod shaders;
 mod vbo_packer;
 
-pub use self::geom::Affine;
+pub use self::geom::{Affine, Vec2};
 pub use self::renderer::*;
@@ -93,7 +93,6 @@ impl RenderDims {
         )
     }
 
-    #[allow(dead_code)]
     pub fn to_app_pos_f64(&self, raw_x: f64, raw_y: f64) -> (f64, f64) {
         let raw_y = self.native_dims.1 as f64 - raw_y;
         (
@@ -78,11 +78,15 @@ impl<A: AppAssetId> Renderer<A> {
         self.b.dims.to_app_pos(raw_x, raw_y)
     }
 
-    #[allow(dead_code)]
     pub(crate) fn to_app_pos_f64(&self, raw_x: f64, raw_y: f64) -> (f64, f64) {
         self.b.dims.to_app_pos_f64(raw_x, raw_y)
     }
 
+    pub(crate) fn draw_cursor(&mut self, pos: (f64, f64), sprite_id: u16) {
+        let affine = Affine::translate(pos.0, pos.1);
+        self.b.append_sprite(&mut self.c, &affine, sprite_id, 0.);
+    }
+
     pub(crate) fn flush(&mut self) {
         self.b.flush(&mut self.c);
     }
@@ -121,8 +125,12 @@ impl<'a, A: AppAssetId + 'a> SpriteRenderer<'a, A> {
     /// `flash_ratio`, capped between `0.0` and `1.0`, controls how much blending occurs with the
     /// color white (`0.0` means use the image unaltered, `1.0` means use white completely).
     pub fn draw_flash(&mut self, affine: &Affine, sprite: A::Sprite, flash_ratio: f64) {
+        self.draw_id(affine, sprite.id_u16(), flash_ratio);
+    }
+
+    pub(crate) fn draw_id(&mut self, affine: &Affine, sprite_id: u16, flash_ratio: f64) {
         self.r
             .b
-            .append_sprite(&mut self.r.c, affine, sprite.id_u16(), flash_ratio);
+            .append_sprite(&mut self.r.c, affine, sprite_id, flash_ratio);
     }
 }
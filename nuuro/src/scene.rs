@@ -0,0 +1,450 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scenes, splitting an app into states such as a title menu, the game and a pause menu.
+//!
+//! Each state implements `Scene`, which mirrors `App`, and a `SceneStack` holding them
+//! is passed to `nuuro::run`. Only the scene on top of the stack advances and receives
+//! input; its callbacks return a `SceneChange` to push a scene on top of it, pop it,
+//! or replace it. Scenes marked as overlays, such as a pause menu, are rendered over
+//! the scenes beneath them.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use nuuro::asset_id::AppAssetId;
+//! use nuuro::renderer::Renderer;
+//! use nuuro::scene::{Scene, SceneChange};
+//! use nuuro::{AppContext, KeyCode};
+//!
+//! struct Game;
+//! struct Pause;
+//!
+//! impl<A: AppAssetId> Scene<A> for Game {
+//!     fn advance(&mut self, _seconds: f64, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+//!         SceneChange::None
+//!     }
+//!
+//!     fn key_down(&mut self, key: KeyCode, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+//!         match key {
+//!             KeyCode::Escape => SceneChange::push(Pause),
+//!             _ => SceneChange::None,
+//!         }
+//!     }
+//!
+//!     fn render(&mut self, renderer: &mut Renderer<A>, _ctx: &AppContext<A>) {
+//!         renderer.clear((0, 0, 0));
+//!     }
+//! }
+//!
+//! impl<A: AppAssetId> Scene<A> for Pause {
+//!     fn is_overlay(&self) -> bool {
+//!         true
+//!     }
+//!
+//!     fn advance(&mut self, _seconds: f64, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+//!         SceneChange::None
+//!     }
+//!
+//!     fn key_down(&mut self, key: KeyCode, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+//!         match key {
+//!             KeyCode::Escape => SceneChange::Pop,
+//!             _ => SceneChange::None,
+//!         }
+//!     }
+//!
+//!     fn render(&mut self, _renderer: &mut Renderer<A>, _ctx: &AppContext<A>) {
+//!         // draw the menu over the game
+//!     }
+//! }
+//! ```
+//!
+//! The app is then started with `nuuro::run(info, SceneStack::new(Game))`.
+
+use crate::asset_id::AppAssetId;
+use crate::gamepad::{GamepadButton, GamepadId};
+use crate::input::KeyCode;
+use crate::renderer::Renderer;
+use crate::{App, AppContext};
+
+/// A state of the app managed by a `SceneStack`, mirroring `App`.
+///
+/// Callbacks receiving input are only invoked on the scene on top of the stack,
+/// while the lifecycle callbacks (`focus_changed`, `suspended`, `resumed`,
+/// `music_finished`, `gamepad_connected` and `gamepad_disconnected`)
+/// are invoked on every scene, from the bottom of the stack to the top.
+pub trait Scene<A: AppAssetId> {
+    /// Invoked when the scene is added to the stack, default behavior is a no-op.
+    fn start(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when the scene is removed from the stack, default behavior is a no-op.
+    fn stop(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when another scene is pushed on top of this one, default behavior is a no-op.
+    fn covered(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Invoked when this scene is on top of the stack again, after the scene covering it
+    /// was popped, default behavior is a no-op.
+    fn uncovered(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// Returns `true` if the scenes beneath this one should be rendered before it,
+    /// default is `false`.
+    ///
+    /// Scenes beneath an overlay are rendered but do not advance.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Advances the scene state by a given amount of `seconds`, see `App::advance`.
+    fn advance(&mut self, seconds: f64, ctx: &mut AppContext<A>) -> SceneChange<A>;
+
+    /// Invoked when a key or mouse button is pressed down, see `App::key_down`.
+    fn key_down(&mut self, key: KeyCode, ctx: &mut AppContext<A>) -> SceneChange<A>;
+
+    /// Invoked when a key or mouse button is released, default behavior is a no-op.
+    fn key_up(&mut self, _key: KeyCode, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+        SceneChange::None
+    }
+
+    /// Invoked when the mouse wheel is scrolled, see `App::mouse_wheel`,
+    /// default behavior is a no-op.
+    fn mouse_wheel(&mut self, _dx: f64, _dy: f64, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+        SceneChange::None
+    }
+
+    /// Invoked with typed text, see `App::text_input`, default behavior is a no-op.
+    fn text_input(&mut self, _text: &str, _ctx: &mut AppContext<A>) -> SceneChange<A> {
+        SceneChange::None
+    }
+
+    /// Invoked when a gamepad button is pressed, default behavior is a no-op.
+    fn gamepad_button_down(
+        &mut self,
+        _id: GamepadId,
+        _button: GamepadButton,
+        _ctx: &mut AppContext<A>,
+    ) -> SceneChange<A> {
+        SceneChange::None
+    }
+
+    /// Invoked when a gamepad button is released, default behavior is a no-op.
+    fn gamepad_button_up(
+        &mut self,
+        _id: GamepadId,
+        _button: GamepadButton,
+        _ctx: &mut AppContext<A>,
+    ) -> SceneChange<A> {
+        SceneChange::None
+    }
+
+    /// See `App::focus_changed`, default behavior is a no-op.
+    fn focus_changed(&mut self, _focused: bool, _ctx: &mut AppContext<A>) {}
+
+    /// See `App::suspended`, default behavior is a no-op.
+    fn suspended(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// See `App::resumed`, default behavior is a no-op.
+    fn resumed(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// See `App::music_finished`, default behavior is a no-op.
+    fn music_finished(&mut self, _ctx: &mut AppContext<A>) {}
+
+    /// See `App::gamepad_connected`, default behavior is a no-op.
+    fn gamepad_connected(&mut self, _id: GamepadId, _ctx: &mut AppContext<A>) {}
+
+    /// See `App::gamepad_disconnected`, default behavior is a no-op.
+    fn gamepad_disconnected(&mut self, _id: GamepadId, _ctx: &mut AppContext<A>) {}
+
+    /// Renders the scene in its current state.
+    fn render(&mut self, renderer: &mut Renderer<A>, ctx: &AppContext<A>);
+}
+
+/// Change to the `SceneStack` requested by the scene on top of it.
+pub enum SceneChange<A: AppAssetId> {
+    /// Keeps the stack as it is.
+    None,
+    /// Pushes a scene on top of the current one.
+    Push(Box<dyn Scene<A>>),
+    /// Removes the current scene, closing the app if it was the last one.
+    Pop,
+    /// Replaces the current scene.
+    Replace(Box<dyn Scene<A>>),
+    /// Removes every scene and starts over with the given one.
+    ReplaceAll(Box<dyn Scene<A>>),
+}
+
+impl<A: AppAssetId> SceneChange<A> {
+    /// Shorthand for `SceneChange::Push(Box::new(scene))`.
+    pub fn push<S: Scene<A> + 'static>(scene: S) -> SceneChange<A> {
+        SceneChange::Push(Box::new(scene))
+    }
+
+    /// Shorthand for `SceneChange::Replace(Box::new(scene))`.
+    pub fn replace<S: Scene<A> + 'static>(scene: S) -> SceneChange<A> {
+        SceneChange::Replace(Box::new(scene))
+    }
+
+    /// Shorthand for `SceneChange::ReplaceAll(Box::new(scene))`.
+    pub fn replace_all<S: Scene<A> + 'static>(scene: S) -> SceneChange<A> {
+        SceneChange::ReplaceAll(Box::new(scene))
+    }
+}
+
+/// Visual effect played while a `SceneStack` switches scenes.
+///
+/// The change is applied halfway through the transition, so the first half is
+/// drawn over the old scenes and the second half over the new ones. The transition plays
+/// in real time regardless of `AppContext::pause` and `AppContext::set_time_scale`.
+///
+/// During a transition, scenes are not advanced and `key_down`, `mouse_wheel`, `text_input`
+/// and `gamepad_button_down` are not delivered. `key_up` and `gamepad_button_up` are still
+/// delivered to the top scene, so that keys held across a scene change are released,
+/// but the scene changes they return are ignored.
+pub trait Transition<A: AppAssetId> {
+    /// Duration of the transition in seconds.
+    fn duration(&self) -> f64;
+
+    /// Renders the transition over the scenes, with `progress` going from `0.0` to `1.0`.
+    fn render(&mut self, progress: f64, renderer: &mut Renderer<A>, ctx: &AppContext<A>);
+}
+
+struct ActiveTransition<A: AppAssetId> {
+    change: Option<SceneChange<A>>,
+    elapsed: f64,
+}
+
+/// Stack of scenes, implementing `App` by forwarding to the scenes.
+pub struct SceneStack<A: AppAssetId> {
+    scenes: Vec<Box<dyn Scene<A>>>,
+    transition: Option<Box<dyn Transition<A>>>,
+    active_transition: Option<ActiveTransition<A>>,
+}
+
+impl<A: AppAssetId> SceneStack<A> {
+    /// Creates a stack holding the `initial` scene, which is started along with the app.
+    pub fn new<S: Scene<A> + 'static>(initial: S) -> SceneStack<A> {
+        SceneStack {
+            scenes: vec![Box::new(initial)],
+            transition: None,
+            active_transition: None,
+        }
+    }
+
+    /// Specifies a transition played on every scene change (default is none).
+    pub fn transition<T: Transition<A> + 'static>(mut self, transition: T) -> Self {
+        self.transition = Some(Box::new(transition));
+        self
+    }
+
+    /// Returns the number of scenes on the stack.
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// Returns `true` if the stack holds no scene, which only happens after the last one
+    /// was popped.
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Returns `true` while a transition is being played.
+    pub fn is_transitioning(&self) -> bool {
+        self.active_transition.is_some()
+    }
+
+    // Applies `change` immediately, or starts a transition that applies it halfway through
+    fn request(&mut self, change: SceneChange<A>, ctx: &mut AppContext<A>) {
+        if let SceneChange::None = change {
+            return;
+        }
+        if self.transition.is_some() {
+            self.active_transition = Some(ActiveTransition {
+                change: Some(change),
+                elapsed: 0.,
+            });
+        } else {
+            self.apply(change, ctx);
+        }
+    }
+
+    fn apply(&mut self, change: SceneChange<A>, ctx: &mut AppContext<A>) {
+        match change {
+            SceneChange::None => {}
+            SceneChange::Push(mut scene) => {
+                if let Some(top) = self.scenes.last_mut() {
+                    top.covered(ctx);
+                }
+                scene.start(ctx);
+                self.scenes.push(scene);
+            }
+            SceneChange::Pop => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.stop(ctx);
+                }
+                match self.scenes.last_mut() {
+                    Some(top) => top.uncovered(ctx),
+                    None => ctx.close(),
+                }
+            }
+            SceneChange::Replace(mut scene) => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.stop(ctx);
+                }
+                scene.start(ctx);
+                self.scenes.push(scene);
+            }
+            SceneChange::ReplaceAll(mut scene) => {
+                while let Some(mut top) = self.scenes.pop() {
+                    top.stop(ctx);
+                }
+                scene.start(ctx);
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    // Forwards a callback to the top scene, unless a transition is playing
+    fn forward_to_top<F>(&mut self, ctx: &mut AppContext<A>, f: F)
+    where
+        F: FnOnce(&mut dyn Scene<A>, &mut AppContext<A>) -> SceneChange<A>,
+    {
+        if self.active_transition.is_some() {
+            return;
+        }
+        if let Some(top) = self.scenes.last_mut() {
+            let change = f(top.as_mut(), ctx);
+            self.request(change, ctx);
+        }
+    }
+
+    // Forwards a key or button release to the top scene, even while a transition is playing
+    fn forward_release_to_top<F>(&mut self, ctx: &mut AppContext<A>, f: F)
+    where
+        F: FnOnce(&mut dyn Scene<A>, &mut AppContext<A>) -> SceneChange<A>,
+    {
+        let transitioning = self.active_transition.is_some();
+        if let Some(top) = self.scenes.last_mut() {
+            let change = f(top.as_mut(), ctx);
+            if !transitioning {
+                self.request(change, ctx);
+            }
+        }
+    }
+
+    fn for_each_scene<F>(&mut self, ctx: &mut AppContext<A>, mut f: F)
+    where
+        F: FnMut(&mut dyn Scene<A>, &mut AppContext<A>),
+    {
+        for scene in &mut self.scenes {
+            f(scene.as_mut(), ctx);
+        }
+    }
+}
+
+impl<A: AppAssetId> App<A> for SceneStack<A> {
+    fn start(&mut self, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.start(ctx));
+    }
+
+    fn advance(&mut self, seconds: f64, ctx: &mut AppContext<A>) {
+        if let Some(mut active) = self.active_transition.take() {
+            let duration = self.transition.as_ref().map_or(0., |t| t.duration());
+            active.elapsed += ctx.unscaled_seconds();
+            if active.elapsed >= duration * 0.5 {
+                if let Some(change) = active.change.take() {
+                    self.apply(change, ctx);
+                }
+            }
+            if active.elapsed < duration {
+                self.active_transition = Some(active);
+            }
+            return;
+        }
+        self.forward_to_top(ctx, |scene, ctx| scene.advance(seconds, ctx));
+    }
+
+    fn key_down(&mut self, key: KeyCode, ctx: &mut AppContext<A>) {
+        self.forward_to_top(ctx, |scene, ctx| scene.key_down(key, ctx));
+    }
+
+    fn key_up(&mut self, key: KeyCode, ctx: &mut AppContext<A>) {
+        self.forward_release_to_top(ctx, |scene, ctx| scene.key_up(key, ctx));
+    }
+
+    fn mouse_wheel(&mut self, dx: f64, dy: f64, ctx: &mut AppContext<A>) {
+        self.forward_to_top(ctx, |scene, ctx| scene.mouse_wheel(dx, dy, ctx));
+    }
+
+    fn text_input(&mut self, text: &str, ctx: &mut AppContext<A>) {
+        self.forward_to_top(ctx, |scene, ctx| scene.text_input(text, ctx));
+    }
+
+    fn gamepad_button_down(
+        &mut self,
+        id: GamepadId,
+        button: GamepadButton,
+        ctx: &mut AppContext<A>,
+    ) {
+        self.forward_to_top(ctx, |scene, ctx| scene.gamepad_button_down(id, button, ctx));
+    }
+
+    fn gamepad_button_up(&mut self, id: GamepadId, button: GamepadButton, ctx: &mut AppContext<A>) {
+        self.forward_release_to_top(ctx, |scene, ctx| scene.gamepad_button_up(id, button, ctx));
+    }
+
+    fn focus_changed(&mut self, focused: bool, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.focus_changed(focused, ctx));
+    }
+
+    fn suspended(&mut self, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.suspended(ctx));
+    }
+
+    fn resumed(&mut self, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.resumed(ctx));
+    }
+
+    fn music_finished(&mut self, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.music_finished(ctx));
+    }
+
+    fn gamepad_connected(&mut self, id: GamepadId, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.gamepad_connected(id, ctx));
+    }
+
+    fn gamepad_disconnected(&mut self, id: GamepadId, ctx: &mut AppContext<A>) {
+        self.for_each_scene(ctx, |scene, ctx| scene.gamepad_disconnected(id, ctx));
+    }
+
+    fn render(&mut self, renderer: &mut Renderer<A>, ctx: &AppContext<A>) {
+        // render from the lowest scene that is visible through the overlays above it
+        let first_visible = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first_visible..] {
+            scene.render(renderer, ctx);
+        }
+        if let (Some(active), Some(transition)) = (&self.active_transition, &mut self.transition) {
+            let duration = transition.duration();
+            let progress = if duration > 0. {
+                (active.elapsed / duration).min(1.)
+            } else {
+                1.
+            };
+            transition.render(progress, renderer, ctx);
+        }
+    }
+}
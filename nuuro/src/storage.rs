@@ -0,0 +1,349 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent save data, accessed through `AppContext::storage`.
+//!
+//! Data is stored in named slots of arbitrary size. On desktop targets each slot is a
+//! file in the user data directory (e.g. `~/.local/share/<storage name>/` on Linux),
+//! written atomically so a crash never leaves a half-written save behind.
+//! In the browser slots are kept in `localStorage`.
+//! The storage name is set with `AppInfo::storage_name`.
+//!
+//! Slot contents are wrapped in a small versioned container with a checksum,
+//! so corrupted data is reported instead of being handed to the app.
+//!
+//! Besides raw bytes, any type implementing serde's `Serialize` can be stored with
+//! `Storage::save` (or `AppContext::save`), using a compact binary encoding.
+//! Typed saves are tagged with the schema version set by `AppInfo::save_schema_version`,
+//! and `Storage::load_migrating` allows converting saves written by older versions of
+//! the app.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use serde::{Deserialize, Serialize};
+//! use nuuro::asset_id::AppAssetId;
+//! use nuuro::storage::{self, StorageError};
+//! use nuuro::AppContext;
+//!
+//! // layout written by schema version 0
+//! #[derive(Deserialize)]
+//! struct ProgressV0 {
+//!     level: u32,
+//! }
+//!
+//! // current layout, with `AppInfo::save_schema_version(1)`
+//! #[derive(Serialize, Deserialize)]
+//! struct Progress {
+//!     level: u32,
+//!     coins: u64,
+//! }
+//!
+//! fn load_progress<A: AppAssetId>(ctx: &AppContext<A>) -> Result<Option<Progress>, StorageError> {
+//!     ctx.load_migrating("progress", |version, bytes| match version {
+//!         0 => {
+//!             let old: ProgressV0 = storage::from_bytes(bytes)?;
+//!             Ok(Progress { level: old.level, coins: 0 })
+//!         }
+//!         _ => Err(StorageError::SchemaMismatch { found: version, expected: 1 }),
+//!     })
+//! }
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use bincode::Options;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::core::CoreStorage;
+
+const MAGIC: &[u8; 4] = b"NUUR";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_LEN: usize = 14;
+const MAX_SLOT_LEN: usize = 64;
+
+/// Error returned by `Storage` operations.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The slot name is empty, too long, or contains characters other than ASCII
+    /// letters, digits, `-` and `_`.
+    InvalidSlot(String),
+    /// There is nowhere to persist data, e.g. no home directory could be found or
+    /// `localStorage` is disabled or full.
+    Unavailable,
+    /// Reading or writing the underlying file failed.
+    Io(io::Error),
+    /// The stored data is truncated or damaged.
+    Corrupt,
+    /// The data was written by a newer version of nuuro, using an unknown format version.
+    UnsupportedFormat(u16),
+    /// A value could not be serialized, or the stored data does not match the requested type.
+    Encoding(String),
+    /// A typed save was written with a different schema version than the current one,
+    /// and was not migrated.
+    SchemaMismatch {
+        /// Schema version of the stored data.
+        found: u32,
+        /// Current schema version, see `AppInfo::save_schema_version`.
+        expected: u32,
+    },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::InvalidSlot(slot) => write!(f, "invalid storage slot name: {:?}", slot),
+            StorageError::Unavailable => write!(f, "persistent storage is unavailable"),
+            StorageError::Io(err) => write!(f, "storage i/o error: {}", err),
+            StorageError::Corrupt => write!(f, "stored data is corrupt"),
+            StorageError::UnsupportedFormat(version) => {
+                write!(f, "unsupported storage format version {}", version)
+            }
+            StorageError::Encoding(err) => write!(f, "save data encoding error: {}", err),
+            StorageError::SchemaMismatch { found, expected } => write!(
+                f,
+                "save data has schema version {}, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> StorageError {
+        StorageError::Io(err)
+    }
+}
+
+/// Persistent storage of named save slots.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use nuuro::asset_id::AppAssetId;
+/// # use nuuro::AppContext;
+/// # fn example<A: AppAssetId>(ctx: &mut AppContext<A>) -> Result<(), nuuro::storage::StorageError> {
+/// ctx.storage.write("slot-1", &[1, 2, 3])?;
+/// assert_eq!(ctx.storage.read("slot-1")?, Some(vec![1, 2, 3]));
+/// ctx.storage.remove("slot-1")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Storage {
+    core: CoreStorage,
+    schema_version: u32,
+}
+
+impl Storage {
+    pub(crate) fn new(core: CoreStorage, schema_version: u32) -> Storage {
+        Storage {
+            core,
+            schema_version,
+        }
+    }
+
+    /// Returns the schema version that typed saves are tagged with,
+    /// see `AppInfo::save_schema_version`.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Reads the data stored in `slot`, or `None` if nothing was written to it.
+    pub fn read(&self, slot: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        check_slot(slot)?;
+        match self.core.read(slot)? {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes `data` to `slot`, replacing any previous contents.
+    ///
+    /// Either the old or the new contents survive if the app is interrupted while writing.
+    pub fn write(&mut self, slot: &str, data: &[u8]) -> Result<(), StorageError> {
+        check_slot(slot)?;
+        self.core.write(slot, &encode(data))
+    }
+
+    /// Removes `slot`, doing nothing if it does not exist.
+    pub fn remove(&mut self, slot: &str) -> Result<(), StorageError> {
+        check_slot(slot)?;
+        self.core.remove(slot)
+    }
+
+    /// Returns `true` if data was written to `slot`.
+    pub fn contains(&self, slot: &str) -> Result<bool, StorageError> {
+        check_slot(slot)?;
+        Ok(self.core.read(slot)?.is_some())
+    }
+
+    /// Returns the names of all slots with data, sorted alphabetically.
+    pub fn slots(&self) -> Result<Vec<String>, StorageError> {
+        let mut slots: Vec<String> = self
+            .core
+            .slots()?
+            .into_iter()
+            .filter(|slot| check_slot(slot).is_ok())
+            .collect();
+        slots.sort();
+        Ok(slots)
+    }
+
+    pub(crate) fn write_config(&mut self, text: &str) -> Result<(), StorageError> {
+        self.core.write_config(text.as_bytes())
+    }
+
+    /// Serializes `value` and writes it to `slot`, tagged with the current schema version.
+    pub fn save<T: Serialize>(&mut self, slot: &str, value: &T) -> Result<(), StorageError> {
+        let mut data = vec![0; 4];
+        LittleEndian::write_u32(&mut data, self.schema_version);
+        data.extend(to_bytes(value)?);
+        self.write(slot, &data)
+    }
+
+    /// Reads a value written by `Storage::save`, or `None` if nothing was written to `slot`.
+    ///
+    /// Fails with `StorageError::SchemaMismatch` if the value was saved with a different
+    /// schema version, use `Storage::load_migrating` to convert older saves.
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> Result<Option<T>, StorageError> {
+        let expected = self.schema_version;
+        self.load_migrating(slot, |found, _| {
+            Err(StorageError::SchemaMismatch { found, expected })
+        })
+    }
+
+    /// Reads a value written by `Storage::save`, converting saves with a different schema
+    /// version through `migrate`.
+    ///
+    /// `migrate` receives the schema version of the stored value along with its encoded
+    /// bytes, which can be decoded into the old type with `storage::from_bytes`.
+    /// Migrated values are not written back; call `Storage::save` to do so.
+    pub fn load_migrating<T, F>(&self, slot: &str, migrate: F) -> Result<Option<T>, StorageError>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(u32, &[u8]) -> Result<T, StorageError>,
+    {
+        let data = match self.read(slot)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        if data.len() < 4 {
+            return Err(StorageError::Corrupt);
+        }
+        let version = LittleEndian::read_u32(&data[0..4]);
+        if version == self.schema_version {
+            from_bytes(&data[4..]).map(Some)
+        } else {
+            migrate(version, &data[4..]).map(Some)
+        }
+    }
+}
+
+fn encoding_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Encodes `value` with the compact binary encoding used by `Storage::save`.
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    encoding_options()
+        .serialize(value)
+        .map_err(|err| StorageError::Encoding(err.to_string()))
+}
+
+/// Decodes a value encoded by `storage::to_bytes`, e.g. inside the `migrate` function
+/// passed to `Storage::load_migrating`.
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    encoding_options()
+        .deserialize(bytes)
+        .map_err(|err| StorageError::Encoding(err.to_string()))
+}
+
+fn check_slot(slot: &str) -> Result<(), StorageError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if slot.is_empty() || slot.len() > MAX_SLOT_LEN || !slot.chars().all(valid_char) {
+        Err(StorageError::InvalidSlot(slot.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+// Turns a title into a name usable as a directory or key prefix, e.g. "My Game!" -> "my_game"
+pub(crate) fn default_storage_name(title: &str) -> String {
+    let mut name = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    while name.ends_with('_') {
+        name.pop();
+    }
+    if name.is_empty() {
+        name.push_str("nuuro_app");
+    }
+    name
+}
+
+// Container layout, all little endian:
+//   magic (4 bytes), format version (u16), payload length (u32), payload checksum (u32), payload
+fn encode(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0; HEADER_LEN + data.len()];
+    bytes[0..4].copy_from_slice(MAGIC);
+    LittleEndian::write_u16(&mut bytes[4..6], FORMAT_VERSION);
+    LittleEndian::write_u32(&mut bytes[6..10], data.len() as u32);
+    LittleEndian::write_u32(&mut bytes[10..14], checksum(data));
+    bytes[HEADER_LEN..].copy_from_slice(data);
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+    if bytes.len() < 6 || &bytes[0..4] != MAGIC {
+        return Err(StorageError::Corrupt);
+    }
+    let version = LittleEndian::read_u16(&bytes[4..6]);
+    if version != FORMAT_VERSION {
+        return Err(StorageError::UnsupportedFormat(version));
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(StorageError::Corrupt);
+    }
+    let len = LittleEndian::read_u32(&bytes[6..10]) as usize;
+    let data = &bytes[HEADER_LEN..];
+    if data.len() != len || LittleEndian::read_u32(&bytes[10..14]) != checksum(data) {
+        return Err(StorageError::Corrupt);
+    }
+    Ok(data.to_vec())
+}
+
+// 32-bit FNV-1a, enough to catch truncated or damaged saves
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
@@ -0,0 +1,300 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Procedural sound synthesis, for generating sound effects without audio files.
+//!
+//! Sounds are described by `SynthParams` in the spirit of "sfxr": a waveform,
+//! a volume envelope, a frequency slide and some optional noise.
+//! Generated sounds are registered at runtime with `Audio::add_synth_sound`
+//! and played with `Audio::play_synth_sound`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use nuuro::synth::{SynthParams, Waveform};
+//!
+//! let laser = SynthParams::new(Waveform::Sawtooth, 880.)
+//!                         .envelope(0., 0.05, 0.2)
+//!                         .frequency_slide(-2400.);
+//! let samples = laser.generate();
+//! ```
+
+use std::f64::consts::PI;
+
+/// Sample rate of the sample buffers generated by `SynthParams`.
+pub const SAMPLE_RATE: u32 = 44100;
+
+// Frequency (in Hz) at which a sliding sound is cut off
+const MIN_FREQUENCY: f64 = 20.;
+
+/// Base waveform of a synthesized sound.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Waveform {
+    /// Square wave, its duty cycle is set with `SynthParams::duty_cycle`.
+    Square,
+    Sawtooth,
+    Triangle,
+    Sine,
+    /// Pitched noise, a new random value is picked on every period.
+    Noise,
+}
+
+/// Handle to a sound registered with `Audio::add_synth_sound`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SynthSoundId(pub(crate) u16);
+
+/// Parameters of a synthesized sound effect.
+///
+/// Methods for setting parameters are intended to be chained together like
+/// the builder pattern, in the same way as `AppInfo`.
+#[derive(Debug, Clone)]
+pub struct SynthParams {
+    waveform: Waveform,
+    frequency: f64,
+    frequency_slide: f64,
+    frequency_slide_accel: f64,
+    vibrato_depth: f64,
+    vibrato_speed: f64,
+    duty_cycle: f64,
+    attack: f64,
+    sustain: f64,
+    punch: f64,
+    decay: f64,
+    noise: f64,
+    volume: f64,
+    seed: u32,
+}
+
+impl SynthParams {
+    /// Returns new `SynthParams` for a sound with the given `waveform`
+    /// and starting `frequency` (in Hz).
+    ///
+    /// The default envelope is a short blip (no attack, 0.05 seconds of sustain,
+    /// 0.1 seconds of decay).
+    pub fn new(waveform: Waveform, frequency: f64) -> SynthParams {
+        assert!(
+            (MIN_FREQUENCY..=20000.).contains(&frequency),
+            "unrealistic frequency: {}",
+            frequency
+        );
+        SynthParams {
+            waveform,
+            frequency,
+            frequency_slide: 0.,
+            frequency_slide_accel: 0.,
+            vibrato_depth: 0.,
+            vibrato_speed: 0.,
+            duty_cycle: 0.5,
+            attack: 0.,
+            sustain: 0.05,
+            punch: 0.,
+            decay: 0.1,
+            noise: 0.,
+            volume: 0.5,
+            seed: 1,
+        }
+    }
+
+    /// Preset for a short menu blip.
+    pub fn blip() -> SynthParams {
+        SynthParams::new(Waveform::Square, 880.).envelope(0., 0.04, 0.06)
+    }
+
+    /// Preset for a coin or item pickup.
+    pub fn pickup() -> SynthParams {
+        SynthParams::new(Waveform::Square, 1000.)
+            .envelope(0., 0.03, 0.2)
+            .punch(0.5)
+            .frequency_slide(1200.)
+    }
+
+    /// Preset for a character jump.
+    pub fn jump() -> SynthParams {
+        SynthParams::new(Waveform::Square, 300.)
+            .duty_cycle(0.25)
+            .envelope(0., 0.1, 0.15)
+            .frequency_slide(1500.)
+    }
+
+    /// Preset for a hit or hurt sound.
+    pub fn hit() -> SynthParams {
+        SynthParams::new(Waveform::Sawtooth, 500.)
+            .envelope(0., 0.02, 0.15)
+            .frequency_slide(-2500.)
+            .noise(0.3)
+    }
+
+    /// Preset for an explosion.
+    pub fn explosion() -> SynthParams {
+        SynthParams::new(Waveform::Noise, 1200.)
+            .envelope(0., 0.15, 0.6)
+            .punch(0.8)
+            .frequency_slide(-1500.)
+    }
+
+    /// Specifies the volume envelope: seconds to fade in, to hold and to fade out.
+    pub fn envelope(mut self, attack: f64, sustain: f64, decay: f64) -> Self {
+        assert!(
+            attack >= 0. && sustain >= 0. && decay >= 0.,
+            "envelope durations must not be negative"
+        );
+        assert!(
+            attack + sustain + decay <= 10.,
+            "unrealistic sound duration: {}",
+            attack + sustain + decay
+        );
+        self.attack = attack;
+        self.sustain = sustain;
+        self.decay = decay;
+        self
+    }
+
+    /// Specifies an extra volume boost at the start of the sustain, fading out
+    /// until the decay (default is `0.`, between `0.` and `1.`).
+    pub fn punch(mut self, punch: f64) -> Self {
+        self.punch = punch.clamp(0., 1.);
+        self
+    }
+
+    /// Specifies how fast the frequency changes, in Hz per second (default is `0.`).
+    ///
+    /// The sound is cut off when the frequency drops below 20 Hz.
+    pub fn frequency_slide(mut self, slide: f64) -> Self {
+        self.frequency_slide = slide;
+        self
+    }
+
+    /// Specifies how fast the frequency slide changes, in Hz per second squared
+    /// (default is `0.`).
+    pub fn frequency_slide_accel(mut self, accel: f64) -> Self {
+        self.frequency_slide_accel = accel;
+        self
+    }
+
+    /// Specifies a vibrato, `depth` being a ratio of the current frequency
+    /// and `speed` the vibrato frequency in Hz.
+    pub fn vibrato(mut self, depth: f64, speed: f64) -> Self {
+        self.vibrato_depth = depth.clamp(0., 1.);
+        self.vibrato_speed = speed.max(0.);
+        self
+    }
+
+    /// Specifies the ratio of each period in which a `Waveform::Square` is high
+    /// (default is `0.5`).
+    pub fn duty_cycle(mut self, duty_cycle: f64) -> Self {
+        self.duty_cycle = duty_cycle.clamp(0., 1.);
+        self
+    }
+
+    /// Specifies how much white noise is mixed into the waveform
+    /// (default is `0.`, between `0.` and `1.`).
+    pub fn noise(mut self, noise: f64) -> Self {
+        self.noise = noise.clamp(0., 1.);
+        self
+    }
+
+    /// Specifies the volume of the generated samples (default is `0.5`, between `0.` and `1.`).
+    pub fn volume(mut self, volume: f64) -> Self {
+        self.volume = volume.clamp(0., 1.);
+        self
+    }
+
+    /// Specifies the seed used for noise, so the same parameters always
+    /// generate the same sound (default is `1`).
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Returns the duration of the sound in seconds.
+    pub fn duration(&self) -> f64 {
+        self.attack + self.sustain + self.decay
+    }
+
+    /// Generates the mono sample buffer of the sound, at `SAMPLE_RATE`.
+    pub fn generate(&self) -> Vec<f32> {
+        let sample_count = (self.duration() * SAMPLE_RATE as f64).round() as usize;
+        let dt = 1. / SAMPLE_RATE as f64;
+        let mut rng = XorShift::new(self.seed);
+        let mut samples = Vec::with_capacity(sample_count);
+        let mut phase = 0.;
+        let mut period_noise = rng.next_f64();
+
+        for i in 0..sample_count {
+            let t = i as f64 * dt;
+            let mut frequency = self.frequency
+                + self.frequency_slide * t
+                + 0.5 * self.frequency_slide_accel * t * t;
+            if frequency < MIN_FREQUENCY {
+                break;
+            }
+            if self.vibrato_depth > 0. {
+                frequency *= 1. + self.vibrato_depth * (2. * PI * self.vibrato_speed * t).sin();
+            }
+
+            phase += frequency * dt;
+            if phase >= 1. {
+                phase -= phase.floor();
+                period_noise = rng.next_f64();
+            }
+
+            let wave = match self.waveform {
+                Waveform::Square => {
+                    if phase < self.duty_cycle {
+                        1.
+                    } else {
+                        -1.
+                    }
+                }
+                Waveform::Sawtooth => 1. - 2. * phase,
+                Waveform::Triangle => 1. - 4. * (phase - 0.5).abs(),
+                Waveform::Sine => (2. * PI * phase).sin(),
+                Waveform::Noise => period_noise,
+            };
+            let wave = wave * (1. - self.noise) + rng.next_f64() * self.noise;
+
+            let sample = wave * self.envelope_at(t) * self.volume;
+            samples.push(sample.clamp(-1., 1.) as f32);
+        }
+        samples
+    }
+
+    fn envelope_at(&self, t: f64) -> f64 {
+        if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.sustain {
+            let progress = (t - self.attack) / self.sustain;
+            1. + self.punch * (1. - progress)
+        } else {
+            let progress = (t - self.attack - self.sustain) / self.decay;
+            (1. - progress).max(0.)
+        }
+    }
+}
+
+// Small deterministic random generator, returns values between -1 and 1
+struct XorShift(u32);
+
+impl XorShift {
+    fn new(seed: u32) -> XorShift {
+        XorShift(seed.max(1))
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f64 / u32::MAX as f64 * 2. - 1.
+    }
+}
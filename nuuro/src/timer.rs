@@ -1,24 +1,36 @@
 use std::collections::HashMap;
-use std::sync::Mutex;
 use std::time::Duration;
 
-type TimerId = i64;
+use crate::tween::Tween;
 
-lazy_static! {
-    static ref TIMERS_INSTANCES: Mutex<HashMap<TimerId, TimerInstance>> =
-        Mutex::new(HashMap::new());
-}
+/// Handle to a `Timer` added to a `TimerSet`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
 
-static mut TIMERS_ID: TimerId = 0;
+/// Handle to a `Tween` added to a `TimerSet`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TweenId(u64);
 
-#[derive(Clone)]
 /// Structure that allows calculating the time elapsed since its initialization
 /// or determining whether a certain period of time has elapsed.
+///
+/// A `Timer` only advances when its `advance` method is invoked. Timers added to
+/// `AppContext::timers` advance automatically every frame, right before `App::advance`.
+///
+/// **Note:** unlike in nuuro 0.1.5 and earlier, creating a `Timer` no longer makes it
+/// advance on its own, so a timer that is neither added to a `TimerSet` nor advanced
+/// manually never expires. Timers should be added to `AppContext::timers` instead,
+/// e.g. `let id = ctx.timers.add(Timer::new(500, true))`, and then checked with
+/// `ctx.timers.get(id).unwrap().expired()`.
 pub struct Timer {
-    id: TimerId,
+    current_time: f64,
+    expiration_time: f64,
+    endless: bool,
+    repeating: bool,
+    paused: bool,
+    on_expire: Option<Box<dyn FnMut()>>,
 }
 
-#[allow(dead_code)]
 impl Timer {
     /// Initialize a new `Timer`. If the `expiration_time` parameter equals 0,
     /// an endless` Timer` is started; if the parameter has any value
@@ -27,161 +39,304 @@ impl Timer {
     /// determine if the` Timer` will start at once when initialized
     /// (*note:* if it is an endless timer, it will always be initialized started).
     pub fn new(expiration_time: u64, start_active: bool) -> Timer {
-        unsafe {
-            TIMERS_ID += 1;
-        }
-
-        let timer = TimerInstance::new(expiration_time, start_active, unsafe { TIMERS_ID });
+        let mut timer = Timer {
+            current_time: 0.,
+            expiration_time: 0.,
+            endless: true,
+            repeating: false,
+            paused: false,
+            on_expire: None,
+        };
+        timer.set_time(expiration_time, start_active);
+        timer
+    }
 
-        unsafe {
-            TIMERS_INSTANCES.lock().unwrap().insert(TIMERS_ID, timer);
-        }
+    /// Makes the timer start over each time it expires, instead of staying expired.
+    ///
+    /// A repeating timer is always active, starting right away even if it was created
+    /// inactive. Use `on_expire` to be notified of each cycle.
+    pub fn repeating(mut self) -> Self {
+        self.repeating = true;
+        self.current_time = 0.;
+        self
+    }
 
-        Timer {
-            id: unsafe { TIMERS_ID },
-        }
+    /// Invokes `callback` each time the timer expires.
+    pub fn on_expire<F: FnMut() + 'static>(mut self, callback: F) -> Self {
+        self.on_expire = Some(Box::new(callback));
+        self
     }
 
     /// Reset the elapsed time of the timer to 0, and if it has not started before,
     /// the time will begin to elapse.
-    pub fn reset(&self) {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get_mut(&self.id)
-            .unwrap()
-            .reset();
+    pub fn reset(&mut self) {
+        self.current_time = 0.;
     }
 
     /// Set a new expiration time for the timer and activation on start.
-    pub fn set_time(&self, expiration_time: u64, start_active: bool) {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get_mut(&self.id)
-            .unwrap()
-            .set_time(expiration_time, start_active);
+    ///
+    /// A repeating timer always starts active.
+    pub fn set_time(&mut self, expiration_time: u64, start_active: bool) {
+        self.expiration_time = expiration_time as f64 / 1000.;
+        self.endless = expiration_time == 0;
+        self.current_time = if start_active || self.repeating {
+            0.
+        } else {
+            self.expiration_time
+        };
     }
 
     /// Get the timer status. If the expiration time has not been reached, the
     /// timer will be active.
     pub fn active(&self) -> bool {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get(&self.id)
-            .unwrap()
-            .active()
+        self.endless || self.current_time < self.expiration_time
     }
 
     /// Indicates if the timer has expired.
     pub fn expired(&self) -> bool {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get(&self.id)
-            .unwrap()
-            .expired()
+        !self.active()
+    }
+
+    /// Indicates if the timer starts over each time it expires.
+    pub fn is_repeating(&self) -> bool {
+        self.repeating
+    }
+
+    /// Stops advancing the timer until `resume` is invoked.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes a timer stopped with `pause`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Indicates if the timer was stopped with `pause`.
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
 
     /// Returns the time elapsed since the initialization of the last timer cycle.
     pub fn elapsed(&self) -> Duration {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get(&self.id)
-            .unwrap()
-            .elapsed()
+        Duration::from_secs_f64(self.current_time)
     }
 
     /// Returns the time elapsed since the initialization of the last timer cycle
     /// as milliseconds.
     pub fn elapsed_as_millis(&self) -> u64 {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .get(&self.id)
-            .unwrap()
-            .elapsed_as_millis()
+        (self.current_time * 1000.) as u64
     }
-}
 
-impl Drop for Timer {
-    fn drop(&mut self) {
-        TIMERS_INSTANCES
-            .lock()
-            .unwrap()
-            .retain(|&t, _| t != self.id);
+    /// Advances the timer by `seconds`, invoking the `on_expire` callback if it expires.
+    pub fn advance(&mut self, seconds: f64) {
+        if self.paused || !self.active() {
+            return;
+        }
+        self.current_time += seconds.max(0.);
+        if self.endless {
+            return;
+        }
+        let mut expirations = 0;
+        if self.repeating {
+            while self.current_time >= self.expiration_time {
+                self.current_time -= self.expiration_time;
+                expirations += 1;
+            }
+        } else if self.current_time >= self.expiration_time {
+            self.current_time = self.expiration_time;
+            expirations = 1;
+        }
+        if let Some(callback) = self.on_expire.as_mut() {
+            for _ in 0..expirations {
+                callback();
+            }
+        }
     }
 }
 
-struct TimerInstance {
-    current_time: u64,
-    expiration_time: u64,
-    endless: bool,
-
-    pub id: TimerId,
+/// A collection of timers and tweens advancing together, with a shared pause and time scale.
+///
+/// # Example
+///
+/// ```rust
+/// use nuuro::{Timer, TimerSet};
+///
+/// let mut timers = TimerSet::new();
+/// let cooldown = timers.add(Timer::new(500, true));
+///
+/// timers.set_time_scale(0.5);
+/// timers.advance(0.6);
+/// assert!(timers.get(cooldown).unwrap().active());
+///
+/// timers.advance(0.6);
+/// assert!(timers.get(cooldown).unwrap().expired());
+/// ```
+pub struct TimerSet {
+    timers: HashMap<TimerId, Timer>,
+    tweens: HashMap<TweenId, Tween>,
+    next_id: u64,
+    time_scale: f64,
+    paused: bool,
 }
 
-#[allow(dead_code)]
-impl TimerInstance {
-    pub fn new(expiration_time: u64, start_active: bool, id: TimerId) -> TimerInstance {
-        TimerInstance {
-            current_time: if start_active { 0 } else { expiration_time },
-            expiration_time,
-            endless: expiration_time == 0,
+impl Default for TimerSet {
+    fn default() -> TimerSet {
+        TimerSet::new()
+    }
+}
 
-            id,
+impl TimerSet {
+    /// Returns an empty set, with a time scale of `1.0`.
+    pub fn new() -> TimerSet {
+        TimerSet {
+            timers: HashMap::new(),
+            tweens: HashMap::new(),
+            next_id: 0,
+            time_scale: 1.,
+            paused: false,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.current_time = 0;
+    /// Adds `timer` to the set, returning a handle to access it.
+    pub fn add(&mut self, timer: Timer) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.insert(id, timer);
+        id
     }
-    pub fn set_time(&mut self, expiration_time: u64, start_active: bool) {
-        self.current_time = if start_active { 0 } else { expiration_time };
-        self.expiration_time = expiration_time;
-        self.endless = expiration_time == 0;
+
+    /// Removes the timer `id` from the set, returning it.
+    pub fn remove(&mut self, id: TimerId) -> Option<Timer> {
+        self.timers.remove(&id)
     }
 
-    pub fn active(&self) -> bool {
-        if !self.endless {
-            return self.current_time < self.expiration_time;
-        }
-        true
+    /// Returns the timer `id`, or `None` if it was removed.
+    pub fn get(&self, id: TimerId) -> Option<&Timer> {
+        self.timers.get(&id)
     }
-    pub fn expired(&self) -> bool {
-        !self.active()
+
+    /// Returns the timer `id` mutably, or `None` if it was removed.
+    pub fn get_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
+        self.timers.get_mut(&id)
     }
 
-    pub fn elapsed(&self) -> Duration {
-        Duration::from_millis(self.elapsed_as_millis())
+    /// Indicates if the timer `id` is in the set.
+    pub fn contains(&self, id: TimerId) -> bool {
+        self.timers.contains_key(&id)
     }
 
-    pub fn elapsed_as_millis(&self) -> u64 {
-        self.current_time
+    /// Adds `tween` to the set, returning a handle to access it.
+    pub fn add_tween(&mut self, tween: Tween) -> TweenId {
+        let id = TweenId(self.next_id);
+        self.next_id += 1;
+        self.tweens.insert(id, tween);
+        id
     }
 
-    pub fn is_endless(&self) -> bool {
-        self.endless
+    /// Removes the tween `id` from the set, returning it.
+    pub fn remove_tween(&mut self, id: TweenId) -> Option<Tween> {
+        self.tweens.remove(&id)
     }
 
-    pub fn set_endless(&mut self, endless: bool) {
-        self.endless = endless;
+    /// Returns the tween `id`, or `None` if it was removed.
+    pub fn tween(&self, id: TweenId) -> Option<&Tween> {
+        self.tweens.get(&id)
     }
 
-    pub fn update(&mut self, elapsed_time: f64) {
-        let elapsed_time = (elapsed_time * 1000.0) as u64;
-        if self.active() {
-            self.current_time += elapsed_time;
-        } else {
-            self.current_time = self.expiration_time;
+    /// Returns the tween `id` mutably, or `None` if it was removed.
+    pub fn tween_mut(&mut self, id: TweenId) -> Option<&mut Tween> {
+        self.tweens.get_mut(&id)
+    }
+
+    /// Indicates if the tween `id` is in the set.
+    pub fn contains_tween(&self, id: TweenId) -> bool {
+        self.tweens.contains_key(&id)
+    }
+
+    /// Returns the number of timers and tweens in the set.
+    pub fn len(&self) -> usize {
+        self.timers.len() + self.tweens.len()
+    }
+
+    /// Indicates if the set has no timers nor tweens.
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty() && self.tweens.is_empty()
+    }
+
+    /// Removes all timers and tweens.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+        self.tweens.clear();
+    }
+
+    /// Removes the timers that have expired and the tweens that have finished.
+    pub fn remove_expired(&mut self) {
+        self.timers.retain(|_, timer| timer.active());
+        self.tweens.retain(|_, tween| !tween.is_finished());
+    }
+
+    /// Returns the factor applied to the time advancing the timers and tweens.
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets the factor applied to the time advancing the timers and tweens (default is `1.0`),
+    /// e.g. `0.5` for slow motion.
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.);
+    }
+
+    /// Stops advancing all timers and tweens until `resume` is invoked.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the timers and tweens stopped with `pause`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Indicates if the set was stopped with `pause`.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances all timers and tweens by `seconds`, multiplied by the time scale.
+    pub fn advance(&mut self, seconds: f64) {
+        if self.paused {
+            return;
+        }
+        let seconds = seconds * self.time_scale;
+        for timer in self.timers.values_mut() {
+            timer.advance(seconds);
+        }
+        for tween in self.tweens.values_mut() {
+            tween.advance(seconds);
         }
     }
 }
 
-pub(crate) fn update_all(elapsed_time: f64) {
-    for timer in TIMERS_INSTANCES.lock().unwrap().values_mut() {
-        timer.update(elapsed_time);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn repeating_timer_created_inactive_still_fires() {
+        let expirations = Rc::new(Cell::new(0));
+        let counter = expirations.clone();
+        let mut timer = Timer::new(100, false)
+            .repeating()
+            .on_expire(move || counter.set(counter.get() + 1));
+        assert!(timer.active());
+        timer.advance(0.25);
+        assert_eq!(expirations.get(), 2);
+        assert!(timer.active());
+
+        timer.set_time(100, false);
+        timer.advance(0.1);
+        assert_eq!(expirations.get(), 3);
     }
 }
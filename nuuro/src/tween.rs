@@ -0,0 +1,424 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tweens, animating a value along easing curves.
+//!
+//! Tweens added to `AppContext::timers` advance automatically every frame, right before
+//! `App::advance`, so an animation only needs to be declared once and its value read when
+//! rendering. Tweens added to `AppContext::ui_timers` ignore the time scale and pause.
+//!
+//! ```
+//! use nuuro::tween::{Easing, Tween};
+//! use nuuro::TimerSet;
+//!
+//! // slides in from the left, bounces back and forth twice, then settles
+//! let tween = Tween::new(-50., 20., 0.4, Easing::BackOut)
+//!     .then(0., 0.3, Easing::QuadInOut)
+//!     .yoyo()
+//!     .repeat(3)
+//!     .on_complete(|| println!("done"));
+//!
+//! let mut timers = TimerSet::new();
+//! let slide = timers.add_tween(tween);
+//! assert_eq!(timers.tween(slide).unwrap().value(), -50.);
+//!
+//! timers.advance(10.);
+//! assert!(timers.tween(slide).unwrap().is_finished());
+//! ```
+
+use std::f64::consts::PI;
+
+/// Easing curves, mapping the progress of a tween to the progress of its value.
+///
+/// `In` curves start slowly, `Out` curves end slowly and `InOut` curves do both.
+/// `Back` curves overshoot the target slightly, `Elastic` curves oscillate around it
+/// and `Bounce` curves bounce against it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Maps `t`, clamped between `0.0` and `1.0`, along the curve.
+    ///
+    /// The result is `0.0` for `t = 0.0` and `1.0` for `t = 1.0`, but may leave that range
+    /// in between for the `Back` and `Elastic` curves.
+    pub fn apply(self, t: f64) -> f64 {
+        const BACK: f64 = 1.70158;
+        const BACK_IN_OUT: f64 = BACK * 1.525;
+        if t <= 0. {
+            return 0.;
+        } else if t >= 1. {
+            return 1.;
+        }
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t) * (1. - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+            Easing::BackIn => (BACK + 1.) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1. + (BACK + 1.) * (t - 1.).powi(3) + BACK * (t - 1.).powi(2),
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    (2. * t).powi(2) * ((BACK_IN_OUT + 1.) * 2. * t - BACK_IN_OUT) / 2.
+                } else {
+                    ((2. * t - 2.).powi(2) * ((BACK_IN_OUT + 1.) * (2. * t - 2.) + BACK_IN_OUT)
+                        + 2.)
+                        / 2.
+                }
+            }
+            Easing::ElasticIn => {
+                -(2f64.powf(10. * t - 10.)) * ((10. * t - 10.75) * (2. * PI / 3.)).sin()
+            }
+            Easing::ElasticOut => {
+                2f64.powf(-10. * t) * ((10. * t - 0.75) * (2. * PI / 3.)).sin() + 1.
+            }
+            Easing::ElasticInOut => {
+                let wave = ((20. * t - 11.125) * (2. * PI / 4.5)).sin();
+                if t < 0.5 {
+                    -(2f64.powf(20. * t - 10.) * wave) / 2.
+                } else {
+                    2f64.powf(-20. * t + 10.) * wave / 2. + 1.
+                }
+            }
+            Easing::BounceIn => 1. - bounce_out(1. - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1. - bounce_out(1. - 2. * t)) / 2.
+                } else {
+                    (1. + bounce_out(2. * t - 1.)) / 2.
+                }
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f64) -> f64 {
+    const N: f64 = 7.5625;
+    const D: f64 = 2.75;
+    if t < 1. / D {
+        N * t * t
+    } else if t < 2. / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984_375
+    }
+}
+
+/// A value animated over time along one or more easing curves.
+///
+/// A `Tween` only advances when its `advance` method is invoked. Tweens added to a
+/// `TimerSet`, such as `AppContext::timers`, advance along with its timers.
+pub struct Tween {
+    segments: Vec<Segment>,
+    repeat: Repeat,
+    yoyo: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+
+    // time elapsed in the current repetition
+    elapsed: f64,
+    repetitions: u32,
+    finished: bool,
+    paused: bool,
+}
+
+impl Tween {
+    /// Creates a tween going from `from` to `to` in `duration` seconds along `easing`.
+    pub fn new(from: f64, to: f64, duration: f64, easing: Easing) -> Tween {
+        Tween {
+            segments: vec![Segment {
+                from,
+                to,
+                duration: duration.max(0.),
+                easing,
+            }],
+            repeat: Repeat::Times(0),
+            yoyo: false,
+            on_complete: None,
+            elapsed: 0.,
+            repetitions: 0,
+            finished: false,
+            paused: false,
+        }
+    }
+
+    /// Appends a step going from the current target to `to` in `duration` seconds.
+    pub fn then(mut self, to: f64, duration: f64, easing: Easing) -> Self {
+        let from = self.segments.last().unwrap().to;
+        self.segments.push(Segment {
+            from,
+            to,
+            duration: duration.max(0.),
+            easing,
+        });
+        self
+    }
+
+    /// Plays all the steps `times` more times after the first time (default is `0`).
+    pub fn repeat(mut self, times: u32) -> Self {
+        self.repeat = Repeat::Times(times);
+        self
+    }
+
+    /// Plays all the steps over and over, the tween then never finishes.
+    pub fn repeat_forever(mut self) -> Self {
+        self.repeat = Repeat::Forever;
+        self
+    }
+
+    /// Plays every other repetition backwards, going back and forth.
+    pub fn yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    /// Invokes `callback` once the tween finishes.
+    pub fn on_complete<F: FnMut() + 'static>(mut self, callback: F) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> f64 {
+        let mut time = if self.is_reversed() {
+            self.duration() - self.elapsed
+        } else {
+            self.elapsed
+        };
+        for segment in &self.segments {
+            if time < segment.duration {
+                let t = segment.easing.apply(time / segment.duration);
+                return segment.from + (segment.to - segment.from) * t;
+            }
+            time -= segment.duration;
+        }
+        // the end of the steps, also where a reversed repetition starts
+        self.segments.last().unwrap().to
+    }
+
+    /// Returns the progress of the current repetition, between `0.0` and `1.0`.
+    pub fn progress(&self) -> f64 {
+        let duration = self.duration();
+        if duration > 0. {
+            self.elapsed / duration
+        } else {
+            1.
+        }
+    }
+
+    /// Indicates if the tween has finished playing, including all repetitions.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Stops advancing the tween until `resume` is invoked.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes a tween stopped with `pause`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Indicates if the tween was stopped with `pause`.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Plays the tween again from the start, including all repetitions.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.;
+        self.repetitions = 0;
+        self.finished = false;
+    }
+
+    /// Advances the tween by `seconds`, invoking the `on_complete` callback if it finishes.
+    pub fn advance(&mut self, seconds: f64) {
+        if self.finished || self.paused {
+            return;
+        }
+        let duration = self.duration();
+        self.elapsed += seconds.max(0.);
+        while self.elapsed >= duration {
+            let repeats = match self.repeat {
+                Repeat::Times(times) => self.repetitions < times,
+                Repeat::Forever => duration > 0.,
+            };
+            if !repeats {
+                self.elapsed = duration;
+                self.finished = true;
+                if let Some(callback) = self.on_complete.as_mut() {
+                    callback();
+                }
+                return;
+            }
+            self.elapsed -= duration;
+            self.repetitions += 1;
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.segments.iter().map(|segment| segment.duration).sum()
+    }
+
+    fn is_reversed(&self) -> bool {
+        self.yoyo && self.repetitions % 2 == 1
+    }
+}
+
+struct Segment {
+    from: f64,
+    to: f64,
+    duration: f64,
+    easing: Easing,
+}
+
+#[derive(Copy, Clone)]
+enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn overshoot_carries_over_to_the_next_cycles() {
+        let mut tween = Tween::new(0., 10., 1., Easing::Linear).repeat(3);
+        tween.advance(1.25);
+        assert_close(tween.value(), 2.5);
+        tween.advance(2.5);
+        assert_close(tween.value(), 7.5);
+        assert!(!tween.is_finished());
+        tween.advance(0.5);
+        assert!(tween.is_finished());
+        assert_close(tween.value(), 10.);
+        assert_close(tween.progress(), 1.);
+    }
+
+    #[test]
+    fn overshoot_carries_over_across_steps() {
+        let mut tween = Tween::new(0., 10., 1., Easing::Linear).then(20., 2., Easing::Linear);
+        tween.advance(2.);
+        assert_close(tween.value(), 15.);
+    }
+
+    #[test]
+    fn yoyo_turns_around_at_exact_cycle_ends() {
+        let mut tween = Tween::new(0., 10., 1., Easing::Linear)
+            .then(20., 1., Easing::Linear)
+            .yoyo()
+            .repeat(2);
+        tween.advance(2.);
+        assert_close(tween.value(), 20.);
+        tween.advance(0.5);
+        assert_close(tween.value(), 15.);
+        tween.advance(1.5);
+        assert_close(tween.value(), 0.);
+        tween.advance(0.5);
+        assert_close(tween.value(), 5.);
+        tween.advance(1.5);
+        assert!(tween.is_finished());
+        assert_close(tween.value(), 20.);
+    }
+
+    #[test]
+    fn yoyo_finishes_at_the_start_after_an_even_number_of_plays() {
+        let mut tween = Tween::new(0., 10., 1., Easing::QuadIn).yoyo().repeat(1);
+        tween.advance(2.);
+        assert!(tween.is_finished());
+        assert_close(tween.value(), 0.);
+    }
+
+    #[test]
+    fn on_complete_fires_exactly_once() {
+        let completions = Rc::new(Cell::new(0));
+        let counter = completions.clone();
+        let mut tween = Tween::new(0., 1., 0.5, Easing::BounceOut)
+            .repeat(1)
+            .on_complete(move || counter.set(counter.get() + 1));
+        tween.advance(0.5);
+        assert_eq!(completions.get(), 0);
+        tween.advance(10.);
+        assert_eq!(completions.get(), 1);
+        tween.advance(1.);
+        assert_eq!(completions.get(), 1);
+
+        tween.reset();
+        tween.advance(1.);
+        assert_eq!(completions.get(), 2);
+    }
+
+    #[test]
+    fn endless_tween_never_completes() {
+        let completions = Rc::new(Cell::new(0));
+        let counter = completions.clone();
+        let mut tween = Tween::new(0., 1., 0.5, Easing::Linear)
+            .repeat_forever()
+            .on_complete(move || counter.set(counter.get() + 1));
+        tween.advance(100.25);
+        assert!(!tween.is_finished());
+        assert_eq!(completions.get(), 0);
+        assert_close(tween.value(), 0.5);
+    }
+}
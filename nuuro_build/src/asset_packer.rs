@@ -12,12 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
 use crate::atlas::form_atlas;
+use crate::audio::{enumerate_audio, AudioOptions};
 use crate::html;
 use crate::rerun_print;
 
@@ -32,7 +32,8 @@ use crate::rerun_print;
 pub struct AssetPacker {
     assets_dir: PathBuf,
     check_rerun: bool,
-    mp3_fallback: bool,
+    audio: AudioOptions,
+    sound_resample: Option<(u16, u32)>,
     sprites: Option<Vec<String>>,
     music: Option<Vec<String>>,
     sounds: Option<Vec<String>>,
@@ -52,7 +53,8 @@ impl AssetPacker {
             assets_dir: assets_dir.to_path_buf(),
             sprites: None,
             check_rerun: false,
-            mp3_fallback: false,
+            audio: AudioOptions::default(),
+            sound_resample: None,
             music: None,
             sounds: None,
             js: false,
@@ -74,7 +76,7 @@ impl AssetPacker {
         self.check_rerun = true;
     }
 
-    /// Invoke this method to copy `.mp3` files along with `.ogg` files for audio.
+    /// Invoke this method to copy `.mp3` files along with the source files for audio.
     ///
     /// This is useful when compiling to the WASM target architecture.
     /// The Safari browser does not support the `.ogg` file format,
@@ -86,7 +88,61 @@ impl AssetPacker {
             self.music.is_none() && self.sounds.is_none(),
             "cannot set mp3 fallback after audio asset packing has already started"
         );
-        self.mp3_fallback = true;
+        self.audio.mp3_fallback = true;
+    }
+
+    /// Invoke this method to generate every needed audio format from a single source file.
+    ///
+    /// Instead of copying the files found on disk, the preferred source file of each
+    /// asset (`.ogg`, then `.flac`, then `.wav`) is encoded to `.ogg`,
+    /// and also to `.mp3` if `mp3_fallback` is set.
+    /// This requires the `ffmpeg` command to be available when building.
+    ///
+    /// Panics if called after calling methods to pack audio assets.
+    pub fn transcode_audio(&mut self) {
+        assert!(
+            self.music.is_none() && self.sounds.is_none(),
+            "cannot set audio transcoding after audio asset packing has already started"
+        );
+        self.audio.transcode = true;
+    }
+
+    /// Normalizes the loudness of all music and sounds to `target_lufs`
+    /// (e.g. `-16.`, a common target for games).
+    ///
+    /// Implies `transcode_audio`. Normalized audio is encoded at 48 kHz,
+    /// unless `resample_sounds` sets another sample rate.
+    ///
+    /// Panics if called after calling methods to pack audio assets.
+    pub fn normalize_loudness(&mut self, target_lufs: f64) {
+        assert!(
+            (-70. ..=-5.).contains(&target_lufs),
+            "unrealistic target_lufs: {}",
+            target_lufs
+        );
+        self.transcode_audio();
+        self.audio.loudness = Some(target_lufs);
+    }
+
+    /// Downmixes and resamples sound effects to the given number of `channels`
+    /// and `sample_rate`, reducing their size (e.g. `1` channel at `22050` Hz).
+    ///
+    /// Music is left untouched. Implies `transcode_audio`.
+    ///
+    /// Panics if called after calling methods to pack audio assets.
+    pub fn resample_sounds(&mut self, channels: u16, sample_rate: u32) {
+        assert!(
+            (1..=2).contains(&channels),
+            "unsupported channel count: {}",
+            channels
+        );
+        assert!(
+            (8000..=96000).contains(&sample_rate),
+            "unrealistic sample_rate: {}",
+            sample_rate
+        );
+        self.transcode_audio();
+        self.sound_resample = Some((channels, sample_rate));
     }
 
     /// Packs sprite images into an atlas, to be rendered by Nuuro renderer in "sprite" mode.
@@ -123,17 +179,27 @@ impl AssetPacker {
 
     /// Creates handles for and copies music files from `in_dir` to the assets directory.
     ///
-    /// Music files are expected to be in `.ogg` format,
+    /// Music files are expected to be in `.ogg`, `.flac` or `.wav` format,
     /// generating enum handles with the same names as the audio files.
     /// Returns the list of these handles indexed by ID,
     /// in the same order that they appear in the generated enum code.
+    ///
+    /// Each file is copied as "music#.ext", where # is the ID and ext is the
+    /// original extension. If the same name exists in several formats, all of
+    /// them are copied, and a "music.manifest" file lists the available formats
+    /// so the runtime can choose the right file for each platform.
+    /// See `transcode_audio` to generate the formats from a single source file instead.
+    ///
+    /// If an `.ogg` file has a "LOOPSTART" comment, and optionally "LOOPLENGTH" or
+    /// "LOOPEND" (in samples), looping that music plays the start once and then
+    /// repeats the section between these points.
     pub fn music(&mut self, in_dir: &Path) -> &[String] {
         assert!(self.music.is_none(), "self.music(...) was already invoked");
         self.music = Some(enumerate_audio(
             in_dir,
             &self.assets_dir,
             "music",
-            self.mp3_fallback,
+            &self.audio,
             self.check_rerun,
         ));
         self.music.as_ref().unwrap()
@@ -141,10 +207,13 @@ impl AssetPacker {
 
     /// Creates handles for and copies sound files from `in_dir` to the assets directory.
     ///
-    /// Music files are expected to be in `.ogg` format,
+    /// Sound files are expected to be in `.ogg`, `.flac` or `.wav` format,
     /// generating enum handles with the same names as the audio files.
     /// Returns the list of these handles indexed by ID,
     /// in the same order that they appear in the generated enum code.
+    ///
+    /// Files are copied and listed in "sound.manifest" following the same
+    /// naming scheme as `music`.
     pub fn sounds(&mut self, in_dir: &Path) -> &[String] {
         assert!(
             self.sounds.is_none(),
@@ -154,7 +223,10 @@ impl AssetPacker {
             in_dir,
             &self.assets_dir,
             "sound",
-            self.mp3_fallback,
+            &AudioOptions {
+                resample: self.sound_resample,
+                ..self.audio.clone()
+            },
             self.check_rerun,
         ));
         self.sounds.as_ref().unwrap()
@@ -238,7 +310,7 @@ impl AssetPacker {
     }
 }
 
-fn create_file(out_dir: &Path, filename: &str, contents: &str, check_rerun: bool) {
+pub(crate) fn create_file(out_dir: &Path, filename: &str, contents: &str, check_rerun: bool) {
     let out_path = out_dir.join(filename);
     File::create(&out_path)
         .unwrap()
@@ -247,47 +319,6 @@ fn create_file(out_dir: &Path, filename: &str, contents: &str, check_rerun: bool
     rerun_print(check_rerun, &out_path);
 }
 
-fn enumerate_audio(
-    in_dir: &Path,
-    out_dir: &Path,
-    prefix: &str,
-    mp3_fallback: bool,
-    check_rerun: bool,
-) -> Vec<String> {
-    let mut paths: Vec<_> = in_dir
-        .read_dir()
-        .unwrap()
-        .filter_map(|p| p.ok())
-        .map(|p| p.path())
-        .filter(|p| p.extension() == Some(OsStr::new("ogg")))
-        .collect();
-    paths.sort_unstable();
-    for (id, path) in paths.iter().enumerate() {
-        let out_path = out_dir.join(format!("{}{}.{}", prefix, id, "ogg"));
-        copy_file(path, &out_path, check_rerun);
-        if mp3_fallback {
-            copy_file(
-                &path.with_extension("mp3"),
-                &out_path.with_extension("mp3"),
-                check_rerun,
-            );
-        }
-    }
-    paths
-        .iter()
-        .map(|p| p.file_stem().unwrap().to_str().unwrap().to_owned())
-        .collect()
-}
-
-fn copy_file(from: &Path, to: &Path, check_rerun: bool) {
-    rerun_print(check_rerun, from);
-    rerun_print(check_rerun, to);
-    fs::copy(from, to).unwrap_or_else(|err| match err.kind() {
-        io::ErrorKind::NotFound => panic!("Missing file: {:?}", from),
-        _ => panic!("{}", err),
-    });
-}
-
 fn gen_asset_enum(name: &str, ids: &[String]) -> String {
     let mut ids_str = String::new();
     for id in ids {
@@ -0,0 +1,393 @@
+// Copyright 2020-2020 Juan Villacorta
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::asset_packer::create_file;
+use crate::rerun_print;
+
+// Source audio formats accepted for music and sounds, in order of preference
+const AUDIO_FORMATS: [&str; 3] = ["ogg", "flac", "wav"];
+
+// Sample rate of loudness-normalized audio, unless resampling is requested
+const LOUDNORM_SAMPLE_RATE: u32 = 48000;
+
+#[derive(Clone, Default)]
+pub struct AudioOptions {
+    pub mp3_fallback: bool,
+    pub transcode: bool,
+    pub loudness: Option<f64>,
+    pub resample: Option<(u16, u32)>,
+}
+
+impl AudioOptions {
+    fn has_filters(&self) -> bool {
+        self.loudness.is_some() || self.resample.is_some()
+    }
+}
+
+pub fn enumerate_audio(
+    in_dir: &Path,
+    out_dir: &Path,
+    prefix: &str,
+    options: &AudioOptions,
+    check_rerun: bool,
+) -> Vec<String> {
+    let mut names: Vec<_> = in_dir
+        .read_dir()
+        .unwrap()
+        .filter_map(|p| p.ok())
+        .map(|p| p.path())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| AUDIO_FORMATS.iter().any(|f| ext == OsStr::new(f)))
+                .unwrap_or(false)
+        })
+        .map(|p| p.file_stem().unwrap().to_str().unwrap().to_owned())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let mut manifest = String::new();
+    for (id, name) in names.iter().enumerate() {
+        let out_stem = format!("{}{}", prefix, id);
+        let formats = if options.transcode {
+            transcode_audio(in_dir, name, out_dir, &out_stem, options, check_rerun)
+        } else {
+            copy_audio(in_dir, name, out_dir, &out_stem, options, check_rerun)
+        };
+        manifest.push_str(&formats.join(" "));
+        if let Some(points) = read_ogg_loop_points(&in_dir.join(format!("{}.ogg", name))) {
+            manifest.push_str(&format!(
+                " loop:{}:{}:{}",
+                points.start, points.end, points.sample_rate
+            ));
+        }
+        manifest.push('\n');
+    }
+    create_file(
+        out_dir,
+        &format!("{}.manifest", prefix),
+        &manifest,
+        check_rerun,
+    );
+    names
+}
+
+// Copies every available source format, plus the `.mp3` fallback if requested
+fn copy_audio(
+    in_dir: &Path,
+    name: &str,
+    out_dir: &Path,
+    out_stem: &str,
+    options: &AudioOptions,
+    check_rerun: bool,
+) -> Vec<&'static str> {
+    let mut formats = Vec::new();
+    for format in AUDIO_FORMATS.iter() {
+        let path = in_dir.join(format!("{}.{}", name, format));
+        if path.is_file() {
+            let out_path = out_dir.join(format!("{}.{}", out_stem, format));
+            copy_file(&path, &out_path, check_rerun);
+            formats.push(*format);
+        }
+    }
+    if options.mp3_fallback {
+        copy_file(
+            &in_dir.join(format!("{}.mp3", name)),
+            &out_dir.join(format!("{}.mp3", out_stem)),
+            check_rerun,
+        );
+        formats.push("mp3");
+    }
+    formats
+}
+
+// Encodes a single source file into `.ogg`, plus `.mp3` if requested
+fn transcode_audio(
+    in_dir: &Path,
+    name: &str,
+    out_dir: &Path,
+    out_stem: &str,
+    options: &AudioOptions,
+    check_rerun: bool,
+) -> Vec<&'static str> {
+    let source = AUDIO_FORMATS
+        .iter()
+        .map(|format| in_dir.join(format!("{}.{}", name, format)))
+        .find(|path| path.is_file())
+        .unwrap();
+    rerun_print(check_rerun, &source);
+
+    let ogg_path = out_dir.join(format!("{}.ogg", out_stem));
+    if source.extension() == Some(OsStr::new("ogg")) && !options.has_filters() {
+        copy_file(&source, &ogg_path, check_rerun);
+    } else {
+        run_ffmpeg(
+            &source,
+            &ogg_path,
+            options,
+            &["-c:a", "libvorbis", "-q:a", "5"],
+        );
+        rerun_print(check_rerun, &ogg_path);
+    }
+    let mut formats = vec!["ogg"];
+    if options.mp3_fallback {
+        let mp3_path = out_dir.join(format!("{}.mp3", out_stem));
+        run_ffmpeg(
+            &source,
+            &mp3_path,
+            options,
+            &["-c:a", "libmp3lame", "-q:a", "4"],
+        );
+        rerun_print(check_rerun, &mp3_path);
+        formats.push("mp3");
+    }
+    formats
+}
+
+fn run_ffmpeg(from: &Path, to: &Path, options: &AudioOptions, codec_args: &[&str]) {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(from)
+        .arg("-vn");
+    if let Some(loudness) = options.loudness {
+        command
+            .arg("-af")
+            .arg(format!("loudnorm=I={}:TP=-1.5:LRA=11", loudness));
+    }
+    if let Some((channels, _)) = options.resample {
+        command.arg("-ac").arg(channels.to_string());
+    }
+    // the loudnorm filter upsamples to 192 kHz, so a sample rate is always set with it
+    let sample_rate = match (options.resample, options.loudness) {
+        (Some((_, sample_rate)), _) => Some(sample_rate),
+        (None, Some(_)) => Some(LOUDNORM_SAMPLE_RATE),
+        (None, None) => None,
+    };
+    if let Some(sample_rate) = sample_rate {
+        command.arg("-ar").arg(sample_rate.to_string());
+    }
+    command.args(codec_args).arg(to);
+
+    let status = command.status().unwrap_or_else(|err| match err.kind() {
+        io::ErrorKind::NotFound => panic!("ffmpeg is required for audio transcoding"),
+        _ => panic!("{}", err),
+    });
+    assert!(status.success(), "ffmpeg failed to transcode {:?}", from);
+}
+
+fn copy_file(from: &Path, to: &Path, check_rerun: bool) {
+    rerun_print(check_rerun, from);
+    rerun_print(check_rerun, to);
+    fs::copy(from, to).unwrap_or_else(|err| match err.kind() {
+        io::ErrorKind::NotFound => panic!("Missing file: {:?}", from),
+        _ => panic!("{}", err),
+    });
+}
+
+// Section of a track that is looped, in samples
+#[derive(Debug, PartialEq)]
+struct LoopPoints {
+    start: u64,
+    end: u64,
+    sample_rate: u32,
+}
+
+// Reads the "LOOPSTART" and "LOOPLENGTH" (or "LOOPEND") comments of an `.ogg` file.
+// The loop ends at the end of the track if only "LOOPSTART" is present.
+fn read_ogg_loop_points(path: &Path) -> Option<LoopPoints> {
+    parse_ogg_loop_points(&fs::read(path).ok()?)
+}
+
+fn parse_ogg_loop_points(data: &[u8]) -> Option<LoopPoints> {
+    // Only the identification and comment headers are collected,
+    // but every page is read to find the total number of samples
+    let mut packets = vec![Vec::new()];
+    let mut total_samples = 0;
+    let mut pos = 0;
+    while data.get(pos..pos + 4) == Some(b"OggS") {
+        let header = data.get(pos..pos + 27)?;
+        let granule = LittleEndian::read_u64(&header[6..14]);
+        let segment_count = header[26] as usize;
+        let segments = data.get(pos + 27..pos + 27 + segment_count)?;
+        let mut body = pos + 27 + segment_count;
+        for &len in segments {
+            let len = len as usize;
+            if packets.len() <= 2 {
+                let segment = data.get(body..body + len)?;
+                packets.last_mut().unwrap().extend_from_slice(segment);
+                if len < 255 {
+                    packets.push(Vec::new());
+                }
+            }
+            body += len;
+        }
+        // a granule position of -1 means that no packet ends on this page
+        if granule != u64::MAX {
+            total_samples = granule;
+        }
+        pos = body;
+    }
+
+    let ident = &packets[0];
+    if ident.len() < 16 || &ident[..7] != b"\x01vorbis" {
+        return None;
+    }
+    let sample_rate = LittleEndian::read_u32(&ident[12..16]);
+    let comments = parse_vorbis_comments(packets.get(1)?)?;
+
+    let start: u64 = comments.get("LOOPSTART")?.parse().ok()?;
+    let end = if let Some(length) = comments.get("LOOPLENGTH") {
+        start.checked_add(length.parse().ok()?)?
+    } else if let Some(end) = comments.get("LOOPEND") {
+        end.parse().ok()?
+    } else {
+        total_samples
+    };
+    if start < end && end <= total_samples {
+        Some(LoopPoints {
+            start,
+            end,
+            sample_rate,
+        })
+    } else {
+        None
+    }
+}
+
+fn parse_vorbis_comments(packet: &[u8]) -> Option<HashMap<String, String>> {
+    if packet.get(..7)? != b"\x03vorbis" {
+        return None;
+    }
+    let mut pos = 7;
+    let read_field = |pos: &mut usize| -> Option<&[u8]> {
+        let len = LittleEndian::read_u32(packet.get(*pos..*pos + 4)?) as usize;
+        let field = packet.get(*pos + 4..*pos + 4 + len)?;
+        *pos += 4 + len;
+        Some(field)
+    };
+    read_field(&mut pos)?; // vendor
+    let count = LittleEndian::read_u32(packet.get(pos..pos + 4)?);
+    pos += 4;
+
+    let mut comments = HashMap::new();
+    for _ in 0..count {
+        let comment = String::from_utf8_lossy(read_field(&mut pos)?).into_owned();
+        if let Some(split) = comment.find('=') {
+            let (key, value) = comment.split_at(split);
+            comments.insert(key.to_uppercase(), value[1..].trim().to_owned());
+        }
+    }
+    Some(comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ogg_page(granule: u64, packet: &[u8]) -> Vec<u8> {
+        assert!(packet.len() < 255);
+        let mut page = b"OggS\x00\x00".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&[0; 12]); // serial number, page sequence and checksum
+        page.push(1);
+        page.push(packet.len() as u8);
+        page.extend_from_slice(packet);
+        page
+    }
+
+    fn vorbis_file(comments: &[&str], total_samples: u64) -> Vec<u8> {
+        let mut ident = b"\x01vorbis".to_vec();
+        ident.extend_from_slice(&0u32.to_le_bytes()); // version
+        ident.push(2); // channels
+        ident.extend_from_slice(&44100u32.to_le_bytes());
+        ident.extend_from_slice(&[0; 14]); // bitrates, block sizes and framing
+
+        let mut comment = b"\x03vorbis".to_vec();
+        let vendor = b"test";
+        comment.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        comment.extend_from_slice(vendor);
+        comment.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for text in comments {
+            comment.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            comment.extend_from_slice(text.as_bytes());
+        }
+        comment.push(1); // framing
+
+        let mut data = ogg_page(0, &ident);
+        data.extend(ogg_page(0, &comment));
+        data.extend(ogg_page(total_samples, &[0; 16]));
+        data
+    }
+
+    #[test]
+    fn reads_loop_start_and_length() {
+        let data = vorbis_file(
+            &["TITLE=Theme", "LOOPSTART=44100", "looplength=88200"],
+            441000,
+        );
+        assert_eq!(
+            parse_ogg_loop_points(&data),
+            Some(LoopPoints {
+                start: 44100,
+                end: 132300,
+                sample_rate: 44100,
+            })
+        );
+    }
+
+    #[test]
+    fn loops_until_the_end_without_length() {
+        let data = vorbis_file(&["LOOPSTART=1000"], 441000);
+        assert_eq!(
+            parse_ogg_loop_points(&data).map(|points| points.end),
+            Some(441000)
+        );
+    }
+
+    #[test]
+    fn ignores_files_without_loop_points() {
+        assert_eq!(
+            parse_ogg_loop_points(&vorbis_file(&["TITLE=Theme"], 441000)),
+            None
+        );
+        assert_eq!(parse_ogg_loop_points(b"not an ogg file"), None);
+    }
+
+    #[test]
+    fn ignores_overflowing_loop_length() {
+        let data = vorbis_file(
+            &["LOOPSTART=44100", "LOOPLENGTH=18446744073709551615"],
+            441000,
+        );
+        assert_eq!(parse_ogg_loop_points(&data), None);
+    }
+
+    #[test]
+    fn ignores_truncated_headers() {
+        let data = vorbis_file(&["LOOPSTART=44100", "LOOPLENGTH=88200"], 441000);
+        let headers_len = data.len() - 27 - 1 - 16;
+        for len in 0..headers_len {
+            assert_eq!(parse_ogg_loop_points(&data[..len]), None, "length {}", len);
+        }
+    }
+}
@@ -21,9 +21,9 @@
 //   onload: invoked when the app has finished loading
 //   onquit: invoked when a quit event is signalled from the app
 //   onerror(err): invoked if an error is thrown at any point
-//   readCookie() -> str: if cookie save data is used, this is a function that reads
+//   readCookie() -> str: if the deprecated cookie save data is used, this is a function that reads
 //                        cookie save data as a string, or null
-//   writeCookie(str): if cookie save data is used, this is a function that writes
+//   writeCookie(str): if the deprecated cookie save data is used, this is a function that writes
 //                     cookie save data as a string
 // Returns a handle with the following:
 //   restart: function (with no arguments) that can be called to resume an app
@@ -45,6 +45,7 @@ function nuuro(args) {
   Module.loadingAudioCount = 0;
   Module.currentlyRunning = false;
   Module.appQuit = false;
+  Module.textInputActive = false;
 
   function nuuroFail(err) {
     if (nuuroIsBroken) { return; }
@@ -63,10 +64,20 @@ function nuuro(args) {
 
   try {
     function bytesToBase64(bytes) {
-      return btoa(String.fromCharCode.apply(null, bytes)).replace(/\=/g, '.');
+      // converted in chunks, as passing too many arguments to fromCharCode throws a RangeError
+      let binary = "";
+      for (let i = 0; i < bytes.length; i += 0x8000) {
+        binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+      }
+      return btoa(binary).replace(/\=/g, '.');
     }
     function base64ToBytes(base64) {
-      return new Uint8Array(atob(base64.replace(/\./g, '=')).split("").map(function(c) { return c.charCodeAt(0); }));
+      const binary = atob(base64.replace(/\./g, '='));
+      const bytes = new Uint8Array(binary.length);
+      for (let i = 0; i < binary.length; i++) {
+        bytes[i] = binary.charCodeAt(i);
+      }
+      return bytes;
     }
 
     function readCookieBytes() {
@@ -80,6 +91,16 @@ function nuuro(args) {
 
     const floatSize = 4;
 
+    // Procedurally generated sounds are played through the WebAudio context of Howler,
+    // so they follow its global volume
+    Module.synthSounds = [];
+    function audioContext () {
+      if (!Howler.ctx) {
+        Howler.volume(); // sets up the context of Howler
+      }
+      return Howler.ctx;
+    }
+
     function makeKeycodesMap () {
       var result = {};
       const keycodesArray = [
@@ -87,7 +108,18 @@ function nuuro(args) {
         "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z",
         "0", "1", "2", "3", "4", "5", "6", "7", "8", "9",
         "ArrowRight", "ArrowLeft", "ArrowDown", "ArrowUp",
-        "Escape", "Enter", " ", "Backspace", "Delete"
+        "Escape", "Enter", " ", "Backspace", "Delete",
+        "Tab", "Insert", "Home", "End", "PageUp", "PageDown", "CapsLock",
+        "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+        // modifiers and numpad keys are matched against evt.code, so that left/right
+        // keys and numpad digits can be told apart, see `isLocationKey`
+        "ShiftLeft", "ShiftRight", "ControlLeft", "ControlRight",
+        "AltLeft", "AltRight", "MetaLeft", "MetaRight",
+        "Numpad0", "Numpad1", "Numpad2", "Numpad3", "Numpad4",
+        "Numpad5", "Numpad6", "Numpad7", "Numpad8", "Numpad9",
+        "NumpadAdd", "NumpadSubtract", "NumpadMultiply", "NumpadDivide",
+        "NumpadDecimal", "NumpadEnter",
+        "-", "=", "[", "]", "\\", ";", "'", ",", ".", "/", "`"
       ];
       for (var i = 0; i < keycodesArray.length; i++) {
         result[keycodesArray[i]] = i;
@@ -100,6 +132,13 @@ function nuuro(args) {
       for (var i = 0; i < keycodesArray2.length; i++) {
         result[keycodesArray2[i]] = i;
       }
+      const shiftedPunctuation = {
+        "_": "-", "+": "=", "{": "[", "}": "]", "|": "\\", ":": ";",
+        "\"": "'", "<": ",", ">": ".", "?": "/", "~": "`",
+      };
+      for (const key in shiftedPunctuation) {
+        result[key] = result[shiftedPunctuation[key]];
+      }
       console.log(result);
       return result;
     }
@@ -117,6 +156,17 @@ function nuuro(args) {
     gl.bindBuffer(gl.ARRAY_BUFFER, vbo);
 
     var cursorPos = { x: 0, y: 0 };
+    var gamepadStates = {};
+
+    // Hidden text area that receives typed text while text input is active, so that
+    // dead keys, IME composition and on-screen keyboards work as in any other input.
+    const textInput = document.createElement("textarea");
+    textInput.setAttribute("autocomplete", "off");
+    textInput.setAttribute("autocapitalize", "off");
+    textInput.setAttribute("spellcheck", "false");
+    textInput.style.cssText = "position: absolute; left: 0; top: 0; width: 1px; height: 1px;" +
+      " opacity: 0; border: none; padding: 0; resize: none; pointer-events: none;";
+    wrapperDiv.appendChild(textInput);
     var touchesPos = [];
 
     function setSpriteAttribPointers () {
@@ -158,29 +208,82 @@ function nuuro(args) {
             Module.currentMusic.stop();
           }
           Module.currentMusic = Module.musics[id];
-          Module.currentMusic.loop(true);
+          Module.currentMusicLoops = true;
           Module.currentMusic.volume(volume);
-          Module.currentMusic.play();
+          if (Module.musicManifest[id] && Module.musicManifest[id].loop) {
+            // the start is played once as the "intro" sprite, then the "loop" sprite repeats
+            Module.currentMusic.loop(false);
+            Module.currentMusicIntro = true;
+            playCurrentMusic("intro");
+          } else {
+            Module.currentMusic.loop(true);
+            Module.currentMusicIntro = false;
+            playCurrentMusic();
+          }
         },
         nuuroWasmPlayMusic: function (id, volume) {
           if (Module.currentMusic != null) {
             Module.currentMusic.stop();
           }
           Module.currentMusic = Module.musics[id];
+          Module.currentMusicLoops = false;
           Module.currentMusic.loop(false);
           Module.currentMusic.volume(volume);
-          Module.currentMusic.play();
+          Module.currentMusicIntro = false;
+          playCurrentMusic();
         },
         nuuroWasmStopMusic: function () {
           if (Module.currentMusic != null) {
             Module.currentMusic.stop();
             Module.currentMusic = null;
           }
+          Module.currentMusicIntro = false;
+        },
+        nuuroWasmSetMusicVolume: function (volume) {
+          if (Module.currentMusic != null) {
+            Module.currentMusic.volume(volume);
+          }
+        },
+        nuuroWasmSetMusicPaused: function (paused) {
+          Module.musicPaused = paused != 0;
+          if (Module.currentMusic != null) {
+            if (Module.musicPaused) {
+              Module.currentMusic.pause();
+            } else {
+              Module.currentMusic.play(Module.currentMusicSoundId);
+            }
+          }
+        },
+        nuuroWasmIsAudioAvailable: function () {
+          return !Howler.noAudio && !(Howler.ctx && Howler.ctx.state === "closed");
         },
         nuuroWasmPlaySound: function (id, volume) {
           Module.sounds[id].volume(volume);
           Module.sounds[id].play();
         },
+        nuuroWasmAddSynthSound: function (size, dataPtr, sampleRate) {
+          const ctx = audioContext();
+          if (ctx) {
+            const samples = new Float32Array(Module.memory.buffer, dataPtr, size);
+            const buffer = ctx.createBuffer(1, Math.max(size, 1), sampleRate);
+            buffer.getChannelData(0).set(samples);
+            Module.synthSounds.push(buffer);
+          } else {
+            Module.synthSounds.push(null); // WebAudio is not available
+          }
+          return Module.synthSounds.length - 1;
+        },
+        nuuroWasmPlaySynthSound: function (id, volume) {
+          const ctx = audioContext();
+          if (!ctx || !Module.synthSounds[id]) { return; }
+          const source = ctx.createBufferSource();
+          const gain = ctx.createGain();
+          source.buffer = Module.synthSounds[id];
+          gain.gain.value = volume;
+          source.connect(gain);
+          gain.connect(Howler.masterGain || ctx.destination);
+          source.start();
+        },
         nuuroWasmSpriteAtlasBinSize: function () {
           return Module.spriteAtlas.length;
         },
@@ -222,9 +325,83 @@ function nuuro(args) {
             return false;
           }
         },
+        nuuroWasmSetTextInput: function (active) {
+          Module.textInputActive = active != 0;
+          textInput.value = "";
+          if (Module.textInputActive) {
+            textInput.focus({ preventScroll: true });
+          } else {
+            textInput.blur();
+          }
+        },
+        nuuroWasmSetCursorVisible: function (visible) {
+          canvas.style.cursor = visible != 0 ? "" : "none";
+        },
+        nuuroWasmRequestPointerLock: function () {
+          if (canvas.requestPointerLock) {
+            canvas.requestPointerLock();
+          }
+        },
+        nuuroWasmExitPointerLock: function () {
+          if (document.exitPointerLock) {
+            document.exitPointerLock();
+          }
+        },
+        nuuroWasmIsPointerLocked: function () {
+          return document.pointerLockElement === canvas;
+        },
         nuuroWasmWriteCookie: function (size, dataPtr) {
           writeCookie(bytesToBase64(new Uint8Array(Module.memory.buffer, dataPtr, size)));
         },
+        // Storage queries return the size of their result, which is then copied into
+        // memory by nuuroWasmStorageResultFill; -1 means no data, -2 that storage failed
+        nuuroWasmStorageRead: function (keySize, keyPtr) {
+          try {
+            const value = window.localStorage.getItem(readStr(keyPtr, keySize));
+            if (value === null) { return -1; }
+            Module.storageResult = base64ToBytes(value);
+            return Module.storageResult.length;
+          } catch(err) {
+            return -2;
+          }
+        },
+        nuuroWasmStorageWrite: function (keySize, keyPtr, size, dataPtr) {
+          try {
+            const value = bytesToBase64(new Uint8Array(Module.memory.buffer, dataPtr, size));
+            window.localStorage.setItem(readStr(keyPtr, keySize), value);
+            return true;
+          } catch(err) {
+            return false;
+          }
+        },
+        nuuroWasmStorageRemove: function (keySize, keyPtr) {
+          try {
+            window.localStorage.removeItem(readStr(keyPtr, keySize));
+            return true;
+          } catch(err) {
+            return false;
+          }
+        },
+        nuuroWasmStorageList: function (prefixSize, prefixPtr) {
+          try {
+            const prefix = readStr(prefixPtr, prefixSize);
+            var slots = [];
+            for (var i = 0; i < window.localStorage.length; i++) {
+              const key = window.localStorage.key(i);
+              if (key !== null && key.startsWith(prefix)) {
+                slots.push(key.substring(prefix.length));
+              }
+            }
+            Module.storageResult = new TextEncoder().encode(slots.join("\n"));
+            return Module.storageResult.length;
+          } catch(err) {
+            return -2;
+          }
+        },
+        nuuroWasmStorageResultFill: function (bufferPtr) {
+          new Uint8Array(Module.memory.buffer).set(Module.storageResult, bufferPtr);
+          Module.storageResult = null;
+        },
         nuuroWasmConsoleLog: function (string) {
           console.log(`%c${readCStr(string)}`, "font-weight:bold;");
         },
@@ -244,6 +421,32 @@ function nuuro(args) {
       tryStart();
     }).catch(nuuroFail);
 
+    // Each line lists the available formats of an asset, and optionally its loop points
+    // in samples, e.g. "ogg mp3 loop:44100:882000:44100"
+    function parseManifestLine(line) {
+      var entry = { formats: [], loop: null };
+      for (const token of line.split(" ")) {
+        if (token.startsWith("loop:")) {
+          const [start, end, sampleRate] = token.substring(5).split(":").map(Number);
+          entry.loop = { start: 1000 * start / sampleRate, end: 1000 * end / sampleRate };
+        } else if (token.length > 0) {
+          entry.formats.push(token);
+        }
+      }
+      return entry;
+    }
+
+    function fetchAudioManifest(prefix) {
+      fetch(`${prefix}.manifest`).then(response =>
+        response.ok ? response.text() : ""
+      ).then(text => {
+        Module[`${prefix}Manifest`] = text.split("\n").map(parseManifestLine);
+        tryStart();
+      }).catch(nuuroFail);
+    }
+    fetchAudioManifest("music");
+    fetchAudioManifest("sound");
+
     const spriteImage = new Image();
     spriteImage.onload = function () {
       try {
@@ -276,6 +479,16 @@ function nuuro(args) {
         Module.nuuroWasmKeyEvent = mod.exports.nuuroWasmKeyEvent;
         Module.nuuroWasmMouseEvent = mod.exports.nuuroWasmMouseEvent;
         Module.nuuroWasmTouchEvent = mod.exports.nuuroWasmTouchEvent;
+        Module.nuuroWasmMouseWheel = mod.exports.nuuroWasmMouseWheel;
+        Module.nuuroWasmMouseMotion = mod.exports.nuuroWasmMouseMotion;
+        Module.nuuroWasmTextInput = mod.exports.nuuroWasmTextInput;
+        Module.nuuroWasmFocusChanged = mod.exports.nuuroWasmFocusChanged;
+        Module.nuuroWasmVisibilityChanged = mod.exports.nuuroWasmVisibilityChanged;
+        Module.nuuroWasmMusicFinished = mod.exports.nuuroWasmMusicFinished;
+        Module.nuuroWasmGamepadConnected = mod.exports.nuuroWasmGamepadConnected;
+        Module.nuuroWasmGamepadDisconnected = mod.exports.nuuroWasmGamepadDisconnected;
+        Module.nuuroWasmGamepadButton = mod.exports.nuuroWasmGamepadButton;
+        Module.nuuroWasmGamepadAxis = mod.exports.nuuroWasmGamepadAxis;
         Module.nuuroWasmMusicCount = mod.exports.nuuroWasmMusicCount;
         Module.nuuroWasmSoundCount = mod.exports.nuuroWasmSoundCount;
         Module.nuuroWasmSpriteVertSrc = mod.exports.nuuroWasmSpriteVertSrc;
@@ -345,22 +558,78 @@ function nuuro(args) {
 
     function initAudioArray (prefix, count, loop) {
       Module.loadingAudioCount += count;
+      const manifest = Module[`${prefix}Manifest`];
       var result = new Array(count);
       for (var i = 0; i < count; i++) {
         let audioSrc = `${prefix}${i}`;
-        result[i] = new Howl({
-          src: [`${audioSrc}.ogg`, `${audioSrc}.mp3`],
+        // Howler picks the first format the browser can play, assets packed
+        // without a manifest are assumed to be .ogg files with .mp3 fallbacks
+        let entry = manifest[i] || { formats: [], loop: null };
+        let formats = entry.formats.length > 0 ? entry.formats : ["ogg", "mp3"];
+        let sprite = undefined;
+        if (entry.loop) {
+          sprite = {
+            intro: [0, entry.loop.end],
+            loop: [entry.loop.start, entry.loop.end - entry.loop.start, true],
+          };
+        }
+        const howl = new Howl({
+          src: formats.map(format => `${audioSrc}.${format}`),
           loop: loop,
+          sprite: sprite,
           onload: function () {
+            if (sprite) {
+              // Howler only defines the whole track as a sprite when given no sprites,
+              // it is needed to play the music once from start to end
+              sprite.__default = [0, 1000 * howl.duration()];
+            }
             Module.loadingAudioCount -= 1;
             tryStart2();
           },
-          onloaderror: function() { nuuroFail("failed to load " + audioSrc); }
+          onloaderror: function() {
+            if (Howler.noAudio) {
+              // audio is not supported by the browser, the app runs silently
+              Module.loadingAudioCount -= 1;
+              tryStart2();
+            } else {
+              nuuroFail("failed to load " + audioSrc);
+            }
+          }
         });
+        if (prefix === "music") {
+          howl.on("end", function () { handleMusicEnd(howl); });
+        }
+        result[i] = howl;
       }
       return result;
     }
 
+    // Plays `Module.currentMusic`, remembering the Howler sound id so that it can be
+    // resumed after being paused, and keeping it paused while the app is inactive.
+    function playCurrentMusic(sprite) {
+      Module.currentMusicSoundId = Module.currentMusic.play(sprite);
+      if (Module.musicPaused) {
+        Module.currentMusic.pause(Module.currentMusicSoundId);
+      }
+    }
+
+    function handleMusicEnd(howl) {
+      if (Module.currentlyRunning && Module.currentMusic === howl) {
+        try {
+          if (Module.currentMusicIntro) {
+            Module.currentMusicIntro = false;
+            playCurrentMusic("loop");
+          } else if (!Module.currentMusicLoops) {
+            Module.currentMusic = null;
+            const continuing = Module.nuuroWasmMusicFinished();
+            if (!continuing) {
+              quitApp();
+            }
+          }
+        } catch(err) { nuuroFail(err); }
+      }
+    }
+
     function updateLoadProgress () {
       if (!nuuroIsBroken && onloadprogress) {
         var coreCount = 0;
@@ -382,7 +651,8 @@ function nuuro(args) {
 
     function tryStart () {
       updateLoadProgress();
-      if (!nuuroIsBroken && Module.spriteAtlas && Module.memory && Module.spriteTex) {
+      if (!nuuroIsBroken && Module.spriteAtlas && Module.memory && Module.spriteTex &&
+          Module.musicManifest && Module.soundManifest) {
         if (!Module.nuuroWasmIsAppDefined()) {
           Module.main();
           if (!Module.nuuroWasmIsAppDefined()) {
@@ -421,9 +691,18 @@ function nuuro(args) {
           requestAnimationFrame(updateAndDraw);
           document.addEventListener('keydown', e => handleKeyEvent(e, true));
           document.addEventListener('keyup', e => handleKeyEvent(e, false));
+          document.addEventListener('keypress', handleKeyPress);
+          window.addEventListener('focus', () => handleFocusChange(true));
+          window.addEventListener('blur', () => handleFocusChange(false));
+          document.addEventListener('visibilitychange', handleVisibilityChange);
+          handleFocusChange(document.hasFocus());
+          handleVisibilityChange();
+          textInput.addEventListener('input', handleTextAreaInput);
+          textInput.addEventListener('compositionend', handleCompositionEnd);
           canvas.addEventListener('mousemove', e => handleMouseMotion(e));
           canvas.addEventListener('mousedown', e => handleMouseEvent(e, true));
           canvas.addEventListener('mouseup', e => handleMouseEvent(e, false));
+          canvas.addEventListener('wheel', handleMouseWheel, { passive: false });
           canvas.addEventListener('touchstart', handleTouchStart, false);
           canvas.addEventListener('touchend', handleTouchEnd, false);
           canvas.addEventListener('touchcancel', handleTouchEnd, false);
@@ -432,9 +711,57 @@ function nuuro(args) {
       }
     }
 
+    // The Gamepad API has no events for button and axis changes, so gamepads are
+    // polled once per frame and only the differences are sent to the app.
+    // Button and axis indices follow the "standard" gamepad mapping.
+    function pollGamepads() {
+      if (!navigator.getGamepads) { return true; }
+      const gamepads = navigator.getGamepads();
+      var connected = {};
+      for (var i = 0; i < gamepads.length; i++) {
+        const gamepad = gamepads[i];
+        if (gamepad && gamepad.connected) {
+          connected[gamepad.index] = gamepad;
+        }
+      }
+      for (const index in gamepadStates) {
+        if (!connected[index]) {
+          delete gamepadStates[index];
+          if (!Module.nuuroWasmGamepadDisconnected(index)) { return false; }
+        }
+      }
+      for (const index in connected) {
+        const gamepad = connected[index];
+        var state = gamepadStates[index];
+        if (!state) {
+          state = { buttons: [], axes: [] };
+          gamepadStates[index] = state;
+          if (!Module.nuuroWasmGamepadConnected(index)) { return false; }
+        }
+        for (var b = 0; b < Math.min(gamepad.buttons.length, 17); b++) {
+          const value = gamepad.buttons[b].value;
+          if (state.buttons[b] !== value) {
+            state.buttons[b] = value;
+            if (!Module.nuuroWasmGamepadButton(index, b, value)) { return false; }
+          }
+        }
+        for (var a = 0; a < Math.min(gamepad.axes.length, 4); a++) {
+          const value = gamepad.axes[a];
+          if (state.axes[a] !== value) {
+            state.axes[a] = value;
+            if (!Module.nuuroWasmGamepadAxis(index, a, value)) { return false; }
+          }
+        }
+      }
+      return true;
+    }
+
     function updateAndDraw(now) {
       if (nuuroIsBroken) { return; }
       try {
+        if (Module.currentlyRunning && !pollGamepads()) {
+          quitApp();
+        }
         if (Module.currentlyRunning) {
           resizeCanvas();
           const continuing = Module.nuuroWasmUpdateAndDraw(
@@ -451,11 +778,20 @@ function nuuro(args) {
       } catch(err) { nuuroFail(err); }
     }
 
+    // Keys whose KeyCode depends on where they are on the keyboard rather than the layout
+    function isLocationKey(code) {
+      return /^(Shift|Control|Alt|Meta|Numpad)/.test(code);
+    }
+
     function handleKeyEvent(evt, down) {
       if (Module.currentlyRunning) {
         try {
-          evt.preventDefault();
-          const code = keycodes[evt.key];
+          // while text input is active, printable keys must reach the text area or keypress
+          if (!Module.textInputActive || evt.key.length > 1) {
+            evt.preventDefault();
+          }
+          // like the native build, other keys are reported according to the layout
+          const code = isLocationKey(evt.code) ? keycodes[evt.code] : keycodes[evt.key];
           if (code != undefined) {
             const continuing = Module.nuuroWasmKeyEvent(code, down);
             if (!continuing) {
@@ -466,9 +802,102 @@ function nuuro(args) {
       }
     }
 
+    // Wheel deltas are normalized to notches of a regular mouse wheel,
+    // matching what the native build reports.
+    const WHEEL_PIXELS_PER_NOTCH = 100;
+    const WHEEL_LINES_PER_NOTCH = 3;
+
+    function handleMouseWheel(evt) {
+      if (Module.currentlyRunning) {
+        try {
+          evt.preventDefault();
+          var scale = 1;
+          if (evt.deltaMode == WheelEvent.DOM_DELTA_PIXEL) {
+            scale = 1 / WHEEL_PIXELS_PER_NOTCH;
+          } else if (evt.deltaMode == WheelEvent.DOM_DELTA_LINE) {
+            scale = 1 / WHEEL_LINES_PER_NOTCH;
+          }
+          cursorPos.x = evt.clientX * (canvas.width / canvas.clientWidth);
+          cursorPos.y = evt.clientY * (canvas.height / canvas.clientHeight);
+          // the browser reports positive Y deltas when scrolling down
+          const continuing = Module.nuuroWasmMouseWheel(
+            cursorPos.x,
+            cursorPos.y,
+            evt.deltaX * scale,
+            -evt.deltaY * scale
+          );
+          if (!continuing) {
+            quitApp();
+          }
+        } catch(err) { nuuroFail(err); }
+      }
+    }
+
+    function handleFocusChange(focused) {
+      if (Module.currentlyRunning) {
+        try {
+          const continuing = Module.nuuroWasmFocusChanged(focused);
+          if (!continuing) {
+            quitApp();
+          }
+        } catch(err) { nuuroFail(err); }
+      }
+    }
+
+    function handleVisibilityChange() {
+      if (Module.currentlyRunning) {
+        try {
+          const continuing = Module.nuuroWasmVisibilityChanged(!document.hidden);
+          if (!continuing) {
+            quitApp();
+          }
+        } catch(err) { nuuroFail(err); }
+      }
+    }
+
+    function sendTextInput(text) {
+      if (Module.currentlyRunning && Module.textInputActive && text) {
+        try {
+          const continuing = Module.nuuroWasmTextInput(writeCStr(text));
+          if (!continuing) {
+            quitApp();
+          }
+        } catch(err) { nuuroFail(err); }
+      }
+    }
+
+    function handleTextAreaInput(evt) {
+      if (!evt.isComposing) {
+        sendTextInput(textInput.value);
+        textInput.value = "";
+      }
+    }
+
+    function handleCompositionEnd(evt) {
+      sendTextInput(textInput.value);
+      textInput.value = "";
+    }
+
+    function handleKeyPress(evt) {
+      // only reached when the text area lost focus, e.g. after clicking the canvas
+      if (evt.target !== textInput && !evt.ctrlKey && !evt.metaKey && evt.key.length == 1) {
+        evt.preventDefault();
+        sendTextInput(evt.key);
+      }
+    }
+
     function handleMouseMotion(evt) {
       if (Module.currentlyRunning) {
         try {
+          if (evt.movementX !== undefined) {
+            Module.nuuroWasmMouseMotion(
+              evt.movementX * (canvas.width / canvas.clientWidth),
+              evt.movementY * (canvas.height / canvas.clientHeight)
+            );
+          }
+          if (document.pointerLockElement === canvas) {
+            return;
+          }
           cursorPos.x = evt.clientX * (canvas.width / canvas.clientWidth);
           cursorPos.y = evt.clientY * (canvas.height / canvas.clientHeight);
         } catch(err) { nuuroFail(err); }
@@ -656,6 +1085,10 @@ function nuuro(args) {
       return new TextDecoder("UTF-8").decode(memory.subarray(ptr, endPtr));
     }
 
+    function readStr(ptr, size) {
+      return new TextDecoder("UTF-8").decode(new Uint8Array(Module.memory.buffer, ptr, size));
+    }
+
     function quitApp() {
       Module.currentlyRunning = false;
       currentTouchId = undefined;
@@ -675,9 +1108,10 @@ function nuuro(args) {
           try {
             Module.currentlyRunning = true;
             Module.appQuit = false;
-            if (Module.currentMusic != null) {
-              Module.currentMusic.play();
+            if (Module.currentMusic != null && !Module.musicPaused) {
+              Module.currentMusic.play(Module.currentMusicSoundId);
             }
+            gamepadStates = {};
             Module.nuuroWasmOnRestart();
           } catch(err) { nuuroFail(err); }
         }
@@ -21,7 +21,8 @@
 //! # Example build script
 //!
 //! In the below example, the user should place sprite png files in the "sprites" directory,
-//! music ogg files in the "music" directory, and sound ogg files in the "sounds" directory.
+//! music files in the "music" directory, and sound files in the "sounds" directory.
+//! Audio files may be in `.ogg`, `.flac` or `.wav` format.
 //!
 //! ```rust,no_run
 //! extern crate nuuro_build;
@@ -51,6 +52,7 @@ extern crate lazy_static;
 
 mod asset_packer;
 mod atlas;
+mod audio;
 mod html;
 mod rect_packer;
 